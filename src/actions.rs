@@ -1,23 +1,482 @@
-use std::error::Error;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+};
 
 use crate::{
-    cache::{cache_definition, get_from_cache},
-    dict_api::{get_definition, RequestOptions},
+    cache::{
+        export_to, import_from, Cache, CacheOptions, CacheScope, CacheStats,
+        CachedEntries,
+    },
+    dict_api::WordData,
+    error::WordsError,
     formatter::{print_definition, FormatterConfig},
     get_from_stdin,
-    spell::check_spelling,
-    WordArgs,
+    history::{History, Lookup, Origin},
+    provider::{make_provider, ProviderConfig},
+    spell::{check_spelling, Speller},
+    utils::{format_date, format_time},
+    CacheCommand, WordArgs,
 };
 
 enum WordActions {
     Definition,
     Suggest,
+    ListDatabases,
+    ListStrategies,
     Nothing,
 }
 
+// print names and descriptions one per line
+fn print_pairs(config: &FormatterConfig, pairs: &[(String, String)]) {
+    if config.print {
+        for (name, description) in pairs {
+            println!("{}\t{}", name, description);
+        }
+    }
+}
+
+// a size in bytes in the biggest unit that makes sense
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = size as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn print_stats(stats: &CacheStats) {
+    println!("words\t{}", stats.count);
+    println!("size\t{}", format_size(stats.size));
+
+    if stats.quarantined > 0 {
+        println!("corrupt\t{}", stats.quarantined);
+    }
+
+    if let Some((word, fetched_at)) = stats.oldest.as_ref() {
+        println!("oldest\t{} {}", word, format_date(*fetched_at));
+    }
+
+    if let Some((word, fetched_at)) = stats.newest.as_ref() {
+        println!("newest\t{} {}", word, format_date(*fetched_at));
+    }
+
+    if !stats.top_hits.is_empty() {
+        println!("top hits");
+
+        for (word, hits) in &stats.top_hits {
+            println!("  {}\t{}", word, hits);
+        }
+    }
+}
+
+/// print the cached words that match a phrase with the part that matched
+pub fn search(
+    phrase: &str,
+    limit: usize,
+    scope: CacheScope,
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let style = &config.format_style;
+
+    let results = Cache::open()?.with_scope(scope).search(
+        phrase,
+        limit,
+        (&style.word, &style.reset),
+    )?;
+
+    if results.is_empty() {
+        return Err(WordsError::NotFound(format!(
+            "no cached definitions match {}",
+            phrase
+        )));
+    }
+
+    if config.print {
+        for result in results {
+            println!(
+                "{}{}{}\t{}",
+                style.word, result.word, style.reset, result.snippet
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// run one of the `cache` subcommands
+pub fn cache_command(
+    command: &CacheCommand,
+    scope: CacheScope,
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let cache = Cache::open()?.with_scope(scope);
+
+    match command {
+        CacheCommand::List { pattern } => {
+            let rows = cache.list(pattern)?;
+
+            if config.print {
+                for row in rows {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        row.word,
+                        row.source,
+                        format_date(row.fetched_at),
+                        row.hits
+                    );
+                }
+            }
+        }
+        CacheCommand::Show { word } => {
+            let data = cache.get_raw(word)?.ok_or_else(|| {
+                WordsError::NotFound(format!("{} is not in the cache", word))
+            })?;
+
+            if config.print {
+                println!("{}", data);
+            }
+        }
+        CacheCommand::Rm { word } => {
+            if !cache.remove(word)? {
+                return Err(WordsError::NotFound(format!(
+                    "{} is not in the cache",
+                    word
+                )));
+            }
+        }
+        CacheCommand::Prune { older_than } => {
+            let removed = cache.prune(*older_than)?;
+
+            if config.print {
+                println!("removed {} words", removed);
+            }
+        }
+        CacheCommand::Clear => {
+            let removed = cache.clear()?;
+
+            if config.print {
+                println!("removed {} words", removed);
+            }
+        }
+        CacheCommand::Export { path } => {
+            let count = export_to(&cache, path)?;
+
+            if count.skipped > 0 {
+                eprintln!(
+                    "words_cli: skipped {} words that cant be read",
+                    count.skipped
+                );
+            }
+
+            // stdout is the export so dont print anything else there
+            if config.print && path != "-" {
+                println!("exported {} words", count.exported);
+            }
+        }
+        CacheCommand::Import { path, merge } => {
+            let count = import_from(&cache, path, merge)?;
+
+            if config.print {
+                println!(
+                    "imported {} words, kept {} cached words",
+                    count.imported, count.skipped
+                );
+            }
+        }
+        CacheCommand::Migrate => {
+            let (upgraded, quarantined) = cache.migrate()?;
+
+            if config.print {
+                println!("upgraded {} words", upgraded);
+
+                if quarantined > 0 {
+                    println!("set aside {} corrupt words", quarantined);
+                }
+            }
+        }
+        CacheCommand::Stats => {
+            let stats = cache.stats()?;
+
+            if config.print {
+                print_stats(&stats);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the most spellings to suggest when a word is not found
+const SUGGESTION_LIMIT: usize = 5;
+
+// a not found error with some spellings to try, if aspell cant help or the
+// provider already gave some, like dictd does, the message is left as it is
+fn not_found_error(query: &str, message: String) -> WordsError {
+    if message.contains("did you mean:") {
+        return WordsError::NotFound(message);
+    }
+
+    match check_spelling(query) {
+        Ok(Some(suggestions)) => {
+            let limit = suggestions.len().min(SUGGESTION_LIMIT);
+
+            WordsError::NotFound(format!(
+                "{}, did you mean: {}",
+                message,
+                suggestions[..limit].join(", ")
+            ))
+        }
+        _ => WordsError::NotFound(message),
+    }
+}
+
+// get a definition from the provider and cache it, if the dictionary cant be
+// reached the stale definition is used instead and if it does not have the
+// word that is cached so it is not asked again
+fn fetch_definition(
+    cache: &Cache,
+    provider_conf: &ProviderConfig,
+    query: &str,
+    stale: Option<CachedEntries>,
+) -> Result<(Vec<WordData>, Origin), WordsError> {
+    let err = match make_provider(provider_conf)?.get_definition(query) {
+        Ok(entries) => {
+            cache.cache_definition(query, &entries, &provider_conf.provider)?;
+
+            return Ok((entries, Origin::Network));
+        }
+        Err(err) => err,
+    };
+
+    match (err, stale) {
+        (WordsError::NotFound(message), _) => {
+            cache.cache_not_found(query, &message, &provider_conf.provider)?;
+
+            Err(not_found_error(query, message))
+        }
+        (
+            err @ WordsError::Network(_) | err @ WordsError::RateLimited(_),
+            Some(stale),
+        ) => {
+            eprintln!(
+                "words_cli: {}: {}, using a definition cached {} days ago",
+                err.kind(),
+                err,
+                stale.age() / (24 * 60 * 60)
+            );
+
+            Ok((stale.entries, Origin::StaleCache))
+        }
+        (err, _) => Err(err),
+    }
+}
+
+// save a lookup to the history with how it went, the lookup already worked or
+// failed so not being able to save it is only a warning
+fn record_lookup(mut lookup: Lookup, result: &Result<(), WordsError>) {
+    if let Err(err) = result {
+        lookup.result = err.kind().to_string();
+    }
+
+    if let Err(err) = History::open().and_then(|history| history.add(&lookup)) {
+        eprintln!("words_cli: {}: cant save the history: {}", err.kind(), err);
+    }
+}
+
+/// check a word on each line of the input with one aspell, each word is
+/// printed with its suggestions as soon as it is checked so this can be used
+/// as a stream
+fn suggest_lines<R: BufRead>(
+    speller: &mut Speller,
+    input: R,
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let history = History::open();
+
+    if let Err(err) = history.as_ref() {
+        eprintln!("words_cli: {}: cant save the history: {}", err.kind(), err);
+    }
+
+    for line in input.lines() {
+        let line = line.map_err(|e| {
+            WordsError::BadInput(format!("cant read the words: {}", e))
+        })?;
+
+        let word = line.trim();
+
+        if word.is_empty() {
+            continue;
+        }
+
+        let mut lookup = Lookup::new("suggest", word);
+
+        let suggestions = match speller.check(word) {
+            Ok(Some(suggestions)) => {
+                lookup.result = format!("{} suggestions", suggestions.len());
+
+                suggestions.join(", ")
+            }
+            Ok(None) => {
+                lookup.result = "correct".to_string();
+
+                "correct".to_string()
+            }
+            Err(err @ WordsError::NotFound(_)) => {
+                lookup.result = err.kind().to_string();
+
+                "no suggestions".to_string()
+            }
+            Err(err) => return Err(err),
+        };
+
+        if config.print {
+            println!("{}\t{}", word, suggestions);
+        }
+
+        if let Ok(history) = history.as_ref() {
+            if let Err(err) = history.add(&lookup) {
+                eprintln!(
+                    "words_cli: {}: cant save the history: {}",
+                    err.kind(),
+                    err
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a file or stdin if the path is `-`
+fn open_input(path: &str) -> Result<Box<dyn BufRead>, WordsError> {
+    if path == "-" {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+
+    let file = fs::File::open(path).map_err(|e| {
+        WordsError::BadInput(format!("cant open {}: {}", path, e))
+    })?;
+
+    Ok(Box::new(BufReader::new(file)))
+}
+
+// print every misspelled word in the documents with where it is like a
+// compiler would, finding any is an error so this can fail a CI job
+fn spell_documents(
+    speller: &mut Speller,
+    paths: &[String],
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let mut count = 0;
+
+    for path in paths {
+        let misspellings = speller.check_document(open_input(path)?)?;
+
+        if config.print {
+            for misspelling in &misspellings {
+                let limit = misspelling.suggestions.len().min(SUGGESTION_LIMIT);
+
+                let suggestions = if limit == 0 {
+                    "no suggestions".to_string()
+                } else {
+                    misspelling.suggestions[..limit].join(", ")
+                };
+
+                println!(
+                    "{}:{}:{}: {}: {}",
+                    path,
+                    misspelling.line,
+                    misspelling.column,
+                    misspelling.word,
+                    suggestions
+                );
+            }
+        }
+
+        count += misspellings.len();
+    }
+
+    if count > 0 {
+        return Err(WordsError::Misspelled(format!(
+            "found {} misspelled words",
+            count
+        )));
+    }
+
+    Ok(())
+}
+
+/// check the words in files, or stdin if a path is `-`, one on each line or
+/// every word in them as a document
+pub fn spell(
+    paths: &[String],
+    document: bool,
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let mut speller = Speller::launch()?;
+
+    if document {
+        return spell_documents(&mut speller, paths, config);
+    }
+
+    for path in paths {
+        suggest_lines(&mut speller, open_input(path)?, config)?;
+    }
+
+    Ok(())
+}
+
+/// print the lookups made between since and until or remove them
+pub fn history(
+    since: Option<i64>,
+    until: Option<i64>,
+    clear: bool,
+    config: &FormatterConfig,
+) -> Result<(), WordsError> {
+    let history = History::open()?;
+
+    if clear {
+        let removed = history.clear(since, until)?;
+
+        if config.print {
+            println!("removed {} lookups", removed);
+        }
+
+        return Ok(());
+    }
+
+    if config.print {
+        for lookup in history.list(since, until)? {
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                format_time(lookup.looked_up_at),
+                lookup.action,
+                lookup.query,
+                lookup.headword.as_deref().unwrap_or("-"),
+                lookup.origin.as_deref().unwrap_or("-"),
+                lookup.result
+            );
+        }
+    }
+
+    Ok(())
+}
+
 pub struct WordAction {
     action: WordActions,
     query: String,
+    entry: Option<usize>,
 }
 
 impl Default for WordAction {
@@ -25,12 +484,13 @@ impl Default for WordAction {
         Self {
             action: WordActions::Nothing,
             query: String::new(),
+            entry: None,
         }
     }
 }
 
 impl WordAction {
-    pub fn new(word_args: &WordArgs) -> Self {
+    pub fn new(word_args: &WordArgs) -> Result<Self, WordsError> {
         let mut word_action = WordAction::default();
 
         if let Some(query) = word_args.suggest.as_ref() {
@@ -41,31 +501,85 @@ impl WordAction {
             word_action.query.push_str(query.trim());
 
             word_action.action = WordActions::Definition;
+
+            word_action.entry = word_args.entry;
+        } else if word_args.list_databases {
+            word_action.action = WordActions::ListDatabases;
+        } else if word_args.list_strategies {
+            word_action.action = WordActions::ListStrategies;
         } else {
-            panic!("need something to do");
+            return Err(WordsError::BadInput(
+                "need something to do, try --help".to_string(),
+            ));
         }
 
-        word_action
+        Ok(word_action)
     }
 
     pub fn run(
         &self,
         format_conf: &FormatterConfig,
-    ) -> Result<(), Box<dyn Error>> {
+        provider_conf: &ProviderConfig,
+        cache_opts: &CacheOptions,
+    ) -> Result<(), WordsError> {
         match self.action {
-            WordActions::Definition => self.definition(format_conf),
-            WordActions::Suggest => self.suggest(format_conf),
-            _ => Err(Box::from("nothing to do, this should not happen")),
+            WordActions::Definition => {
+                let mut lookup = Lookup::new("define", &self.query);
+
+                let result = self.definition(
+                    format_conf,
+                    provider_conf,
+                    cache_opts,
+                    &mut lookup,
+                );
+
+                record_lookup(lookup, &result);
+
+                result
+            }
+            // a word on each line of stdin is checked as it comes in
+            WordActions::Suggest if self.query == "-" => {
+                spell(&["-".to_string()], false, format_conf)
+            }
+            WordActions::Suggest => {
+                let mut lookup = Lookup::new("suggest", &self.query);
+
+                let result = self.suggest(format_conf, &mut lookup);
+
+                record_lookup(lookup, &result);
+
+                result
+            }
+            WordActions::ListDatabases => {
+                let provider = make_provider(provider_conf)?;
+
+                print_pairs(format_conf, &provider.databases()?);
+
+                Ok(())
+            }
+            WordActions::ListStrategies => {
+                let provider = make_provider(provider_conf)?;
+
+                print_pairs(format_conf, &provider.strategies()?);
+
+                Ok(())
+            }
+            _ => Err(WordsError::BadInput(
+                "nothing to do, this should not happen".to_string(),
+            )),
         }
     }
 
-    fn suggest(&self, config: &FormatterConfig) -> Result<(), Box<dyn Error>> {
-        let suggest_list = if self.query == "-" {
-            let query_str = get_from_stdin()?;
+    fn suggest(
+        &self,
+        config: &FormatterConfig,
+        lookup: &mut Lookup,
+    ) -> Result<(), WordsError> {
+        let suggest_list = check_spelling(&self.query)?;
 
-            check_spelling(query_str.trim())?
-        } else {
-            check_spelling(&self.query)?
+        lookup.result = match suggest_list.as_ref() {
+            Some(suggest_list) => format!("{} suggestions", suggest_list.len()),
+            None => "correct".to_string(),
         };
 
         if config.print {
@@ -82,27 +596,69 @@ impl WordAction {
     fn definition(
         &self,
         format_conf: &FormatterConfig,
-    ) -> Result<(), Box<dyn Error>> {
-        let query = if self.query == "-" {
-            get_from_stdin()?.trim().to_string()
-        } else {
-            self.query.to_owned()
-        };
+        provider_conf: &ProviderConfig,
+        cache_opts: &CacheOptions,
+        lookup: &mut Lookup,
+    ) -> Result<(), WordsError> {
+        if self.query == "-" {
+            lookup.query = get_from_stdin()?.trim().to_string();
+        }
 
-        let word_data = if let Some(cached_query) = get_from_cache(&query)? {
-            cached_query
-        } else {
-            let request_opts = RequestOptions::default();
+        let query = lookup.query.to_owned();
 
-            let word_data = get_definition(request_opts, &query)?;
+        let cache = Cache::open()?.with_scope(provider_conf.cache_scope());
 
-            cache_definition(&word_data)?;
+        let cached = cache.get_from_cache(&query)?;
 
-            word_data
+        if cached.is_none() {
+            if let Some(not_found) = cache.get_not_found(&query)? {
+                if cache_opts.offline
+                    || !(cache_opts.refresh
+                        || not_found.is_stale(cache_opts.not_found_ttl))
+                {
+                    return Err(not_found_error(&query, not_found.message));
+                }
+            }
+        }
+
+        let (mut entries, origin) = match cached {
+            Some(cached) if cache_opts.offline => {
+                (cached.entries, Origin::Cache)
+            }
+            Some(cached)
+                if !cache_opts.refresh && !cached.is_stale(cache_opts.ttl) =>
+            {
+                (cached.entries, Origin::Cache)
+            }
+            None if cache_opts.offline => {
+                return Err(WordsError::NotFound(format!(
+                    "{} is not in the cache and we are offline",
+                    query
+                )))
+            }
+            stale => fetch_definition(&cache, provider_conf, &query, stale)?,
         };
 
+        lookup.headword = entries.first().map(|entry| entry.word.to_owned());
+        lookup.origin = Some(origin.to_string());
+        lookup.result = "found".to_string();
+
+        // entries are numbered from one when printed
+        if let Some(entry) = self.entry {
+            if entry == 0 || entry > entries.len() {
+                return Err(WordsError::BadInput(format!(
+                    "no entry {}, {} has {} entries",
+                    entry,
+                    query,
+                    entries.len()
+                )));
+            }
+
+            entries = vec![entries.remove(entry - 1)];
+        }
+
         if format_conf.print {
-            print_definition(&format_conf, &word_data);
+            print_definition(format_conf, &entries);
         }
 
         Ok(())
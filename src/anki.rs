@@ -0,0 +1,279 @@
+//! export cached words as flash cards
+//!
+//! the cards are a tab separated file Anki can import, the front is the word
+//! and its phonetic and the back is the definitions and examples the way `-d`
+//! prints them without any style, newlines and indents are written as html
+//! since a field has to fit on one line
+use std::{
+    collections::HashSet,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use tempfile::NamedTempFile;
+
+use crate::{
+    cache::{cache_key, Cache, CacheScope},
+    dict_api::WordData,
+    error::WordsError,
+    formatter::{format_meanings, FormatterConfig},
+    history::History,
+};
+
+/// the words to make cards for
+///
+/// `words` are picked by name, `pattern` is a glob over the cached words and
+/// `since` and `until` pick the words looked up in that time, a word picked
+/// more then once only gets one card, `scope` is the provider and language
+/// the cards are made from
+#[derive(Default)]
+pub struct CardSelection {
+    pub words: Vec<String>,
+    pub pattern: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub scope: CacheScope,
+}
+
+impl CardSelection {
+    fn is_empty(&self) -> bool {
+        self.words.is_empty()
+            && self.pattern.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+}
+
+/// the words in the selection, in the order they were picked
+pub fn select_words(
+    cache: &Cache,
+    selection: &CardSelection,
+) -> Result<Vec<String>, WordsError> {
+    if selection.is_empty() {
+        return Err(WordsError::BadInput(
+            "pick some words by name, with --pattern or with --since and \
+            --until"
+                .to_string(),
+        ));
+    }
+
+    let mut words = selection.words.clone();
+
+    if let Some(pattern) = selection.pattern.as_ref() {
+        words.extend(cache.list(pattern)?.into_iter().map(|row| row.word));
+    }
+
+    if selection.since.is_some() || selection.until.is_some() {
+        let lookups =
+            History::open()?.list(selection.since, selection.until)?;
+
+        words.extend(
+            lookups
+                .into_iter()
+                .filter(|lookup| lookup.result == "found")
+                .filter_map(|lookup| lookup.headword),
+        );
+    }
+
+    let mut seen = HashSet::new();
+
+    words.retain(|word| seen.insert(cache_key(word)));
+
+    Ok(words)
+}
+
+// make text safe to put in an html field
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\t', " ")
+}
+
+// put formatted text on one line keeping its line breaks and indents
+fn to_html(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+
+            let indent = "&nbsp;".repeat(line.len() - trimmed.len());
+
+            format!("{}{}", indent, escape_html(trimmed))
+        })
+        .collect::<Vec<String>>()
+        .join("<br>")
+}
+
+/// the front of a card, the word and the first phonetic any entry has
+pub fn card_front(entries: &[WordData]) -> String {
+    let phonetic = entries
+        .iter()
+        .flat_map(|entry| &entry.phonetics)
+        .map(|phonetic| phonetic.text.trim())
+        .find(|text| !text.is_empty());
+
+    match phonetic {
+        Some(phonetic) => {
+            escape_html(&format!("{} {}", entries[0].word, phonetic))
+        }
+        None => escape_html(&entries[0].word),
+    }
+}
+
+/// the back of a card, the definitions and examples of every entry
+pub fn card_back(entries: &[WordData]) -> String {
+    let mut format_conf = FormatterConfig {
+        synonym_limit: 0,
+        ..Default::default()
+    };
+
+    format_conf.clear_style();
+
+    to_html(&format_meanings(&format_conf, entries))
+}
+
+/// write a card for each word that is cached, giving back how many were
+/// written, words that are not cached are skipped with a warning
+pub fn write_cards<W: Write>(
+    cache: &Cache,
+    words: &[String],
+    mut writer: W,
+) -> Result<usize, WordsError> {
+    let write_error =
+        |e: io::Error| WordsError::BadInput(format!("cant write cards: {}", e));
+
+    // tell anki how to read the file
+    writeln!(writer, "#separator:tab\n#html:true").map_err(write_error)?;
+
+    let mut written = 0;
+
+    for word in words {
+        let entries = match cache.peek(word)? {
+            Some(cached) => cached.entries,
+            None => {
+                eprintln!(
+                    "words_cli: {} is not in the cache, skipping it",
+                    word
+                );
+
+                continue;
+            }
+        };
+
+        writeln!(writer, "{}\t{}", card_front(&entries), card_back(&entries))
+            .map_err(write_error)?;
+
+        written += 1;
+    }
+
+    writer.flush().map_err(write_error)?;
+
+    Ok(written)
+}
+
+/// write the cards for the selected words to a file, or stdout if the path
+/// is `-`
+pub fn export_cards(
+    selection: &CardSelection,
+    path: &str,
+) -> Result<usize, WordsError> {
+    let cache = Cache::open()?.with_scope(selection.scope.clone());
+
+    let words = select_words(&cache, selection)?;
+
+    if path == "-" {
+        let stdout = io::stdout();
+
+        return write_cards(&cache, &words, BufWriter::new(stdout.lock()));
+    }
+
+    let export_error = |e: io::Error| {
+        WordsError::BadInput(format!("cant write {}: {}", path, e))
+    };
+
+    let path = Path::new(path);
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut temp_file = NamedTempFile::new_in(dir).map_err(export_error)?;
+
+    let written = write_cards(&cache, &words, BufWriter::new(&mut temp_file))?;
+
+    temp_file.persist(path).map_err(|e| export_error(e.error))?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_utils::{fake_word_data, TempSetup};
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(
+            to_html("  noun\n    a <b> & \"c\"\n\n      d"),
+            "&nbsp;&nbsp;noun<br>&nbsp;&nbsp;&nbsp;&nbsp;a &lt;b&gt; &amp; \
+            &quot;c&quot;<br><br>&nbsp;&nbsp;&nbsp;&nbsp;&nbsp;&nbsp;d"
+        );
+    }
+
+    #[test]
+    fn test_card_front_and_back() {
+        let mut word_data = fake_word_data();
+
+        assert_eq!(card_front(&[fake_word_data()]), "test test");
+
+        word_data.phonetics.clear();
+
+        assert_eq!(card_front(&[word_data]), "test");
+
+        let back = card_back(&[fake_word_data()]);
+
+        assert!(!back.contains('\x1b'), "styled the back: {}", back);
+        assert!(!back.contains('\n'), "the back is not one line: {}", back);
+        assert!(back.contains("test definition"));
+        assert!(back.contains("test example text"));
+        assert!(!back.contains("synonyms"));
+    }
+
+    #[test]
+    fn test_select_and_write_cards() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
+
+        assert!(select_words(&cache, &CardSelection::default()).is_err());
+
+        let selection = CardSelection {
+            words: vec!["Test".to_string(), "missing".to_string()],
+            pattern: Some("t*".to_string()),
+            ..Default::default()
+        };
+
+        let words = select_words(&cache, &selection).unwrap();
+
+        assert_eq!(words, vec!["Test", "missing"]);
+
+        let mut output = Vec::new();
+
+        assert_eq!(write_cards(&cache, &words, &mut output).unwrap(), 1);
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[..2], ["#separator:tab", "#html:true"]);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].starts_with("test test\t"), "{}", lines[2]);
+    }
+}
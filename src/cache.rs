@@ -1,297 +1,3014 @@
-use std::{error::Error, fs};
+//! the definition cache
+//!
+//! every word is a row in a SQLite database in the data directory with the
+//! json for its entries, where it came from, when it was fetched and how many
+//! times it has been read from the cache
+//!
+//! entries older then the ttl are stale, they are fetched again but kept
+//! around in case the network is down, words imported from wiktionary have
+//! nowhere to be fetched from again so they never go stale
+//!
+//! words the dictionary does not have are saved too so typos dont go to the
+//! network every time, those are kept for a shorter time
+//!
+//! more then one words_cli can use the cache at once, SQLite locks the
+//! database and every write is a transaction so a crash never leaves half a
+//! word behind, a word that cant be read anyway is moved to a quarantine table
+//! and fetched again, and a database that is corrupt is moved out of the way
+//!
+//! the json for a word has a version so the cache can be read after WordData
+//! changes, older json is upgraded when it is read or all at once with
+//! `words_cli cache migrate`
+//!
+//! the cache can be exported to a JSONL file and imported on another machine,
+//! each line is a word with where it came from, when it was fetched and its
+//! aliases
+//!
+//! the definitions, examples and synonyms of every word are kept in a full
+//! text index so words can be found from what they mean
+//!
+//! words are saved under a key made from the headword so "Monster" and
+//! "monster" are the same word, when a query gets a different headword back,
+//! like "ran" giving "run", the query is saved as an alias for it
+//!
+//! the same word can mean something else in another language or dictionary
+//! so words, aliases and missing words are kept apart by the provider and
+//! language they were looked up with, see `CacheScope`
+//!
+//! older versions saved a json file per word in a `cache` directory, those
+//! get moved in to the database the first time it is opened
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use caseless::default_case_fold_str;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{dict_api::WordData, error::WordsError, utils::get_data_path};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS words (
+        word TEXT NOT NULL,
+        provider TEXT NOT NULL DEFAULT 'dictionaryapi',
+        lang TEXT NOT NULL DEFAULT 'en',
+        data TEXT NOT NULL,
+        source TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        hits INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (word, provider, lang)
+    );
+
+    CREATE INDEX IF NOT EXISTS words_fetched_at ON words (fetched_at);
+
+    CREATE TABLE IF NOT EXISTS aliases (
+        alias TEXT NOT NULL,
+        provider TEXT NOT NULL DEFAULT 'dictionaryapi',
+        lang TEXT NOT NULL DEFAULT 'en',
+        word TEXT NOT NULL,
+        PRIMARY KEY (alias, provider, lang),
+        FOREIGN KEY (word, provider, lang)
+            REFERENCES words (word, provider, lang) ON DELETE CASCADE
+    );
+
+    CREATE TABLE IF NOT EXISTS quarantine (
+        word TEXT NOT NULL,
+        provider TEXT NOT NULL DEFAULT 'dictionaryapi',
+        lang TEXT NOT NULL DEFAULT 'en',
+        data TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        quarantined_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS missing (
+        word TEXT NOT NULL,
+        provider TEXT NOT NULL DEFAULT 'dictionaryapi',
+        lang TEXT NOT NULL DEFAULT 'en',
+        message TEXT NOT NULL,
+        source TEXT NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        PRIMARY KEY (word, provider, lang)
+    );
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5 (
+        word UNINDEXED,
+        provider UNINDEXED,
+        lang UNINDEXED,
+        headword,
+        definitions,
+        examples,
+        synonyms,
+        tokenize = 'porter unicode61'
+    );
+
+    CREATE TRIGGER IF NOT EXISTS words_search_delete AFTER DELETE ON words
+    BEGIN
+        DELETE FROM search
+        WHERE word = old.word
+            AND provider = old.provider
+            AND lang = old.lang;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS words_search_rename
+    AFTER UPDATE OF word ON words
+    BEGIN
+        UPDATE search SET word = new.word
+        WHERE word = old.word
+            AND provider = old.provider
+            AND lang = old.lang;
+    END;
+";
+
+/// how much a match in each column of the search index counts, the key and
+/// scope are not searched and a word matching its own name is not much help
+const SEARCH_WEIGHTS: &str = "0.0, 0.0, 0.0, 1.0, 10.0, 4.0, 2.0";
+
+/// the providers that were saved as the source of a word before words were
+/// kept apart by provider, anything else came from dictionaryapi
+const OLD_PROVIDERS: [&str; 3] = ["wordnet", "stardict", "dictd"];
+
+/// the source given to words moved over from the old file cache
+const FILE_CACHE_SOURCE: &str = "file cache";
+
+/// the source given to words imported from a wiktionary dump
+pub const WIKTIONARY_SOURCE: &str = "wiktionary";
+
+/// the version of the json saved for each word, bump this and add a step to
+/// `upgrade_entries` when the json changes
+pub const ENTRY_VERSION: u64 = 2;
+
+/// a change to the database itself
+type DbMigration = fn(&Cache) -> Result<(), WordsError>;
+
+/// the database `user_version` is how many of these have been run
+const DB_MIGRATIONS: &[DbMigration] = &[
+    // the first caches saved words as they were written
+    Cache::normalize_keys,
+    // caches from before the search index
+    Cache::build_search_index,
+    // caches from before words were kept apart by provider and language
+    Cache::add_scopes,
+    // caches from before quarantined words had a scope
+    Cache::add_quarantine_scopes,
+];
+
+/// how long to wait for another words_cli to finish writing
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl From<rusqlite::Error> for WordsError {
+    fn from(err: rusqlite::Error) -> Self {
+        WordsError::Cache(err.to_string())
+    }
+}
+
+// io errors in here are always about the cache
+fn cache_error(err: io::Error) -> WordsError {
+    WordsError::Cache(err.to_string())
+}
+
+// open a connection and make sure the tables are there
+fn connect(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+
+    // readers dont block the writer with a write ahead log
+    conn.query_row("PRAGMA journal_mode = WAL", params![], |_| Ok(()))?;
+
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    conn.execute_batch(SCHEMA)?;
+
+    Ok(conn)
+}
+
+// if the file is not a database or is damaged
+fn is_corrupt(err: &rusqlite::Error) -> bool {
+    match err {
+        rusqlite::Error::SqliteFailure(err, _) => matches!(
+            err.code,
+            ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt
+        ),
+        _ => false,
+    }
+}
+
+// move a corrupt database and its log out of the way so a new one can be
+// made, the old one is kept in case anything can be saved from it
+fn quarantine_database(path: &Path) -> Result<PathBuf, WordsError> {
+    let mut corrupt_path = path.as_os_str().to_owned();
+
+    corrupt_path.push(format!(".corrupt-{}", unix_time(SystemTime::now())));
+
+    let corrupt_path = PathBuf::from(corrupt_path);
+
+    fs::rename(path, &corrupt_path).map_err(cache_error)?;
+
+    for suffix in &["-wal", "-shm"] {
+        let mut log_path = path.as_os_str().to_owned();
+
+        log_path.push(suffix);
+
+        match fs::remove_file(&log_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(cache_error(err))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(corrupt_path)
+}
+
+/// the key a word is saved under
+///
+/// this is the NFC normalized and case folded word with the white space
+/// trimmed so different ways of writing a word find the same definition
+pub fn cache_key(word: &str) -> String {
+    let word: String = word.trim().nfc().collect();
+
+    default_case_fold_str(&word).nfc().collect()
+}
+
+/// seconds since the unix epoch
+pub fn unix_time(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+// seconds since something was fetched
+fn age(fetched_at: i64) -> u64 {
+    (unix_time(SystemTime::now()) - fetched_at).max(0) as u64
+}
+
+/// the provider and language words are looked up in
+///
+/// `provider` is the name of the provider, with the database it looks in for
+/// providers that have more then one, and `lang` is the language code
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheScope {
+    pub provider: String,
+    pub lang: String,
+}
+
+impl Default for CacheScope {
+    fn default() -> Self {
+        Self {
+            provider: "dictionaryapi".to_string(),
+            lang: "en".to_string(),
+        }
+    }
+}
+
+/// how the cache should be used when looking up a word
+///
+/// `ttl` is how long a definition is fresh for before fetching it again
+///
+/// `not_found_ttl` is how long to remember a word was not found
+///
+/// `refresh` will always fetch the definition again
+///
+/// `offline` will never fetch anything and use stale definitions
+#[derive(Debug)]
+pub struct CacheOptions {
+    pub ttl: Duration,
+    pub not_found_ttl: Duration,
+    pub refresh: bool,
+    pub offline: bool,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            // thirty days
+            ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            // one day
+            not_found_ttl: Duration::from_secs(24 * 60 * 60),
+            refresh: false,
+            offline: false,
+        }
+    }
+}
+
+/// the entries for a word and when they were fetched
+#[derive(Debug)]
+pub struct CachedEntries {
+    pub entries: Vec<WordData>,
+    pub source: String,
+    pub fetched_at: i64,
+}
+
+impl CachedEntries {
+    /// seconds since the entries were fetched
+    pub fn age(&self) -> u64 {
+        age(self.fetched_at)
+    }
+
+    /// imported words are never stale, fetching them again would swap them
+    /// for what the provider has
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        self.source != WIKTIONARY_SOURCE && self.age() >= ttl.as_secs()
+    }
+}
+
+/// the error the dictionary gave for a word it does not have
+#[derive(Debug)]
+pub struct CachedNotFound {
+    pub message: String,
+    pub fetched_at: i64,
+}
+
+impl CachedNotFound {
+    pub fn is_stale(&self, ttl: Duration) -> bool {
+        age(self.fetched_at) >= ttl.as_secs()
+    }
+}
+
+/// what to do when an imported word is already cached
+#[derive(Debug, PartialEq)]
+pub enum MergeMode {
+    /// keep which ever was fetched last
+    Newest,
+    /// keep the cached word
+    Existing,
+    /// always use the imported word
+    Overwrite,
+}
+
+impl FromStr for MergeMode {
+    type Err = WordsError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "newest" => Ok(MergeMode::Newest),
+            "existing" => Ok(MergeMode::Existing),
+            "overwrite" => Ok(MergeMode::Overwrite),
+            _ => Err(WordsError::BadInput(format!(
+                "unknown merge mode: {}",
+                mode
+            ))),
+        }
+    }
+}
+
+fn default_provider() -> String {
+    CacheScope::default().provider
+}
+
+fn default_lang() -> String {
+    CacheScope::default().lang
+}
+
+/// a line in an exported cache, exports from before scopes are in the
+/// default one
+#[derive(Deserialize, Serialize, Debug)]
+struct ExportedWord {
+    word: String,
+    #[serde(default = "default_provider")]
+    provider: String,
+    #[serde(default = "default_lang")]
+    lang: String,
+    source: String,
+    fetched_at: i64,
+    #[serde(default)]
+    aliases: Vec<String>,
+    data: Value,
+}
+
+/// how many words an export wrote and how many could not be read
+#[derive(Debug, PartialEq)]
+pub struct ExportCount {
+    pub exported: usize,
+    pub skipped: usize,
+}
+
+/// how many words an import saved and how many it left alone
+#[derive(Debug, PartialEq)]
+pub struct ImportCount {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// a word found by `search` and the part of it that matched
+#[derive(Debug)]
+pub struct SearchResult {
+    pub word: String,
+    pub snippet: String,
+}
+
+/// a word in the cache without its definition
+#[derive(Debug)]
+pub struct CacheRow {
+    pub word: String,
+    pub source: String,
+    pub fetched_at: i64,
+    pub hits: i64,
+}
+
+/// how much is in the cache
+///
+/// `oldest` and `newest` are the words fetched first and last with their
+/// fetch time, `top_hits` are the most looked up words and `quarantined` is
+/// how many words could not be read
+#[derive(Debug)]
+pub struct CacheStats {
+    pub count: usize,
+    pub quarantined: usize,
+    pub size: u64,
+    pub oldest: Option<(String, i64)>,
+    pub newest: Option<(String, i64)>,
+    pub top_hits: Vec<(String, i64)>,
+}
+
+pub struct Cache {
+    conn: Connection,
+    scope: CacheScope,
+}
+
+impl Cache {
+    /// open the cache in the data directory and move any old cache files in
+    /// to it
+    pub fn open() -> Result<Self, WordsError> {
+        let data_path = get_data_path()?;
+
+        // make the data dir regardless, this should only get this far if
+        // either XDG_DATA_HOME or HOME exist, hopefully
+        if !data_path.exists() {
+            fs::create_dir_all(&data_path).map_err(cache_error)?;
+        }
+
+        let cache = Self::open_at(&data_path.join("cache.db"))?;
+
+        let file_cache_path = data_path.join("cache");
+
+        if file_cache_path.is_dir() {
+            cache.migrate_file_cache(&file_cache_path)?;
+        }
+
+        Ok(cache)
+    }
+
+    /// open or make a cache database at the path, if it is corrupt it is
+    /// moved to `<path>.corrupt-<time>` and a new one is made
+    pub fn open_at(path: &Path) -> Result<Self, WordsError> {
+        let conn = match connect(path) {
+            Err(err) if is_corrupt(&err) => {
+                let corrupt_path = quarantine_database(path)?;
+
+                eprintln!(
+                    "words_cli: cache error: {}, moved it to {}",
+                    err,
+                    corrupt_path.display()
+                );
+
+                connect(path)?
+            }
+            conn => conn?,
+        };
+
+        let cache = Self {
+            conn,
+            scope: CacheScope::default(),
+        };
+
+        let version: i64 =
+            cache
+                .conn
+                .query_row("PRAGMA user_version", params![], |row| {
+                    row.get(0)
+                })?;
+
+        for (index, migration) in DB_MIGRATIONS
+            .iter()
+            .enumerate()
+            .skip(version.max(0) as usize)
+        {
+            migration(&cache)?;
+
+            cache.conn.execute_batch(&format!(
+                "PRAGMA user_version = {}",
+                index + 1
+            ))?;
+        }
+
+        Ok(cache)
+    }
+
+    /// look words up in a provider and language, the cache starts in the
+    /// default scope
+    pub fn with_scope(mut self, scope: CacheScope) -> Self {
+        self.scope = scope;
+
+        self
+    }
+
+    /// if the tables have the provider and language columns, a cache made
+    /// before them still has the old tables until `add_scopes` runs
+    fn has_scopes(&self) -> Result<bool, WordsError> {
+        let has_scopes = self.conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM pragma_table_info('words')
+                WHERE name = 'provider'
+            )",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        Ok(has_scopes)
+    }
+
+    /// add the provider and language to the quarantine table, words that were
+    /// already quarantined are left in the default scope since there is no
+    /// way to tell where they came from
+    fn add_quarantine_scopes(&self) -> Result<(), WordsError> {
+        let has_scopes: bool = self.conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM pragma_table_info('quarantine')
+                WHERE name = 'provider'
+            )",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        if has_scopes {
+            return Ok(());
+        }
+
+        self.conn.execute_batch(
+            "ALTER TABLE quarantine
+                ADD COLUMN provider TEXT NOT NULL DEFAULT 'dictionaryapi';
+             ALTER TABLE quarantine
+                ADD COLUMN lang TEXT NOT NULL DEFAULT 'en';",
+        )?;
+
+        Ok(())
+    }
+
+    /// move the words, aliases and missing words in to tables keyed by
+    /// provider and language
+    ///
+    /// the provider comes from the source a word was saved with and
+    /// everything is english since that was all there was
+    fn add_scopes(&self) -> Result<(), WordsError> {
+        // a new cache is made with the scopes already
+        if self.has_scopes()? {
+            return Ok(());
+        }
+
+        let words = self
+            .conn
+            .prepare("SELECT word, data, source, fetched_at, hits FROM words")?
+            .query_map(params![], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<(String, String, String, i64, i64)>, _>>()?;
+
+        let aliases = self
+            .conn
+            .prepare(
+                "SELECT alias, aliases.word, source
+                 FROM aliases JOIN words ON words.word = aliases.word",
+            )?
+            .query_map(params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<(String, String, String)>, _>>()?;
+
+        let missing = self
+            .conn
+            .prepare("SELECT word, message, source, fetched_at FROM missing")?
+            .query_map(params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<(String, String, String, i64)>, _>>()?;
+
+        let scope = |source: &str| CacheScope {
+            provider: if OLD_PROVIDERS.contains(&source) {
+                source.to_string()
+            } else {
+                default_provider()
+            },
+            lang: default_lang(),
+        };
+
+        self.begin()?;
+
+        self.conn.execute_batch(
+            "DROP TRIGGER words_search_delete;
+             DROP TRIGGER words_search_rename;
+             DROP TABLE aliases;
+             DROP TABLE missing;
+             DROP TABLE search;
+             DROP TABLE words;",
+        )?;
+
+        self.conn.execute_batch(SCHEMA)?;
+
+        for (word, data, source, fetched_at, hits) in words {
+            let scope = scope(&source);
+
+            self.conn.execute(
+                "INSERT INTO words
+                    (word, provider, lang, data, source, fetched_at, hits)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    word,
+                    scope.provider,
+                    scope.lang,
+                    data,
+                    source,
+                    fetched_at,
+                    hits
+                ],
+            )?;
+
+            // words that cant be read are quarantined when they are
+            if let Ok((entries, _)) = read_entries(&data) {
+                if !entries.is_empty() {
+                    self.index_entries(&scope, &word, &entries)?;
+                }
+            }
+        }
+
+        for (alias, word, source) in aliases {
+            let scope = scope(&source);
+
+            self.conn.execute(
+                "INSERT INTO aliases (alias, provider, lang, word)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![alias, scope.provider, scope.lang, word],
+            )?;
+        }
+
+        for (word, message, source, fetched_at) in missing {
+            let scope = scope(&source);
+
+            self.conn.execute(
+                "INSERT INTO missing
+                    (word, provider, lang, message, source, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    word,
+                    scope.provider,
+                    scope.lang,
+                    message,
+                    source,
+                    fetched_at
+                ],
+            )?;
+        }
+
+        self.commit()
+    }
+
+    /// move words saved before keys were normalized to there key, when two
+    /// words have the same key the newest one is kept
+    fn normalize_keys(&self) -> Result<(), WordsError> {
+        let rows = self
+            .conn
+            .prepare("SELECT word FROM words ORDER BY fetched_at DESC")?
+            .query_map(params![], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        // the rows are newest first so the first word to get a key keeps it
+        let mut keys = HashSet::new();
+
+        self.begin()?;
+
+        for word in rows {
+            let key = cache_key(&word);
+
+            let newest = keys.insert(key.clone());
+
+            if key == word {
+                // if a newer word took the key this row is already gone
+                continue;
+            }
+
+            if newest {
+                // anything already under the key is older
+                self.conn.execute(
+                    "DELETE FROM words WHERE word = ?1",
+                    params![key],
+                )?;
+
+                self.conn.execute(
+                    "UPDATE words SET word = ?1 WHERE word = ?2",
+                    params![key, word],
+                )?;
+            } else {
+                self.conn.execute(
+                    "DELETE FROM words WHERE word = ?1",
+                    params![word],
+                )?;
+            }
+        }
+
+        self.commit()
+    }
+
+    /// the key of the word a query is saved under, following an alias if
+    /// there is one
+    fn resolve(&self, query: &str) -> Result<String, WordsError> {
+        let key = cache_key(query);
+
+        let word = self
+            .conn
+            .query_row(
+                "SELECT word FROM aliases
+                 WHERE alias = ?1 AND provider = ?2 AND lang = ?3",
+                params![key, self.scope.provider, self.scope.lang],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(word.unwrap_or(key))
+    }
+
+    /// save the entries for a query and where they came from
+    ///
+    /// all the entries for a word are saved together under the first entries
+    /// word, this just overwrites old definitions, if the query is not the
+    /// same word it is saved as an alias
+    pub fn cache_definition(
+        &self,
+        query: &str,
+        entries: &[WordData],
+        source: &str,
+    ) -> Result<(), WordsError> {
+        let scope = &self.scope;
+
+        self.atomically(|| {
+            let key = self.save(
+                scope,
+                entries,
+                source,
+                unix_time(SystemTime::now()),
+            )?;
+
+            let alias = cache_key(query);
+
+            // the word was found this time
+            self.conn.execute(
+                "DELETE FROM missing
+                 WHERE word IN (?1, ?2) AND provider = ?3 AND lang = ?4",
+                params![key, alias, scope.provider, scope.lang],
+            )?;
+
+            if alias != key {
+                self.conn.execute(
+                    "INSERT OR REPLACE INTO aliases
+                        (alias, provider, lang, word)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![alias, scope.provider, scope.lang, key],
+                )?;
+            }
+
+            Ok(())
+        })
+    }
+
+    // save the entries under the key for there word and return the key, what
+    // was saved under the key before is replaced so callers that save many
+    // words, like importing a dump, need to merge or count that themselves
+    fn save(
+        &self,
+        scope: &CacheScope,
+        entries: &[WordData],
+        source: &str,
+        fetched_at: i64,
+    ) -> Result<String, WordsError> {
+        let key = match entries.first() {
+            Some(word_data) => cache_key(&word_data.word),
+            None => {
+                return Err(WordsError::Cache(
+                    "no entries to cache".to_string(),
+                ))
+            }
+        };
+
+        let data_str = write_entries(entries)?;
+
+        // keep the hit count when a word is fetched again
+        self.conn.execute(
+            "INSERT INTO words
+                (word, provider, lang, data, source, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (word, provider, lang) DO UPDATE SET
+                data = excluded.data,
+                source = excluded.source,
+                fetched_at = excluded.fetched_at",
+            params![
+                key,
+                scope.provider,
+                scope.lang,
+                data_str,
+                source,
+                fetched_at
+            ],
+        )?;
+
+        self.index_entries(scope, &key, entries)?;
+
+        Ok(key)
+    }
+
+    // put the text of the entries in the search index
+    fn index_entries(
+        &self,
+        scope: &CacheScope,
+        key: &str,
+        entries: &[WordData],
+    ) -> Result<(), WordsError> {
+        let mut definitions = Vec::new();
+        let mut examples = Vec::new();
+        let mut synonyms = Vec::new();
+
+        for definition in entries
+            .iter()
+            .flat_map(|word_data| &word_data.meanings)
+            .flat_map(|meaning| &meaning.definitions)
+        {
+            definitions.push(definition.definition.as_str());
+
+            if let Some(example) = definition.example.as_ref() {
+                examples.push(example.as_str());
+            }
+
+            if let Some(words) = definition.synonyms.as_ref() {
+                synonyms.extend(words.iter().map(String::as_str));
+            }
+        }
+
+        self.conn.execute(
+            "DELETE FROM search
+             WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+            params![key, scope.provider, scope.lang],
+        )?;
+
+        self.conn.execute(
+            "INSERT INTO search
+                (word, provider, lang, headword, definitions, examples,
+                synonyms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                key,
+                scope.provider,
+                scope.lang,
+                entries[0].word,
+                definitions.join(" / "),
+                examples.join(" / "),
+                synonyms.join(" ")
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// index every word that is already cached
+    fn build_search_index(&self) -> Result<(), WordsError> {
+        // `add_scopes` indexes the words when it moves them
+        if !self.has_scopes()? {
+            return Ok(());
+        }
+
+        let rows = self
+            .conn
+            .prepare("SELECT word, provider, lang, data FROM words")?
+            .query_map(params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<(String, String, String, String)>, _>>()?;
+
+        self.begin()?;
+
+        for (key, provider, lang, data) in rows {
+            // words that cant be read are quarantined when they are
+            if let Ok((entries, _)) = read_entries(&data) {
+                if !entries.is_empty() {
+                    self.index_entries(
+                        &CacheScope { provider, lang },
+                        &key,
+                        &entries,
+                    )?;
+                }
+            }
+        }
+
+        self.commit()
+    }
+
+    /// find words with definitions, examples or synonyms that match the
+    /// phrase, best matches first
+    ///
+    /// each word in the phrase is searched for on its own so a word only
+    /// needs to match some of them, `highlight` goes around the matches in
+    /// the snippet
+    pub fn search(
+        &self,
+        phrase: &str,
+        limit: usize,
+        highlight: (&str, &str),
+    ) -> Result<Vec<SearchResult>, WordsError> {
+        // quote every word so nothing in the phrase is read as fts syntax
+        let query = phrase
+            .split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|term| !term.is_empty())
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+
+        if query.is_empty() {
+            return Err(WordsError::BadInput(
+                "need some words to search for".to_string(),
+            ));
+        }
+
+        let results = self
+            .conn
+            .prepare(&format!(
+                "SELECT headword, snippet(search, -1, ?2, ?3, '...', 12)
+                 FROM search
+                 WHERE search MATCH ?1 AND provider = ?5 AND lang = ?6
+                 ORDER BY bm25(search, {}) LIMIT ?4",
+                SEARCH_WEIGHTS
+            ))?
+            .query_map(
+                params![
+                    query,
+                    highlight.0,
+                    highlight.1,
+                    limit as i64,
+                    self.scope.provider,
+                    self.scope.lang
+                ],
+                |row| {
+                    Ok(SearchResult {
+                        word: row.get(0)?,
+                        snippet: row.get(1)?,
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(results)
+    }
+
+    /// return a definition from the cache if it exists else nothing if it
+    /// doesn't, every time a word is found its hit count goes up
+    pub fn get_from_cache(
+        &self,
+        query: &str,
+    ) -> Result<Option<CachedEntries>, WordsError> {
+        self.read(query, true)
+    }
+
+    /// like `get_from_cache` but the hit count is left alone, for reading
+    /// words the user did not look up like in a quiz or a deck
+    pub fn peek(
+        &self,
+        query: &str,
+    ) -> Result<Option<CachedEntries>, WordsError> {
+        self.read(query, false)
+    }
+
+    fn read(
+        &self,
+        query: &str,
+        count_hit: bool,
+    ) -> Result<Option<CachedEntries>, WordsError> {
+        let key = self.resolve(query)?;
+        let scope = &self.scope;
+
+        let row: Option<(String, String, i64)> = self
+            .conn
+            .query_row(
+                "SELECT data, source, fetched_at FROM words
+                 WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+                params![key, scope.provider, scope.lang],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let (data, source, fetched_at) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        if count_hit {
+            self.conn.execute(
+                "UPDATE words SET hits = hits + 1
+                 WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+                params![key, scope.provider, scope.lang],
+            )?;
+        }
+
+        let entries = match read_entries(&data) {
+            Ok((entries, version)) if !entries.is_empty() => {
+                if version < ENTRY_VERSION {
+                    self.rewrite(scope, &key, &entries)?;
+                }
+
+                entries
+            }
+            // the json is from a newer words_cli, leave it be
+            Err(err @ WordsError::Cache(_)) => return Err(err),
+            result => {
+                let reason = match result {
+                    Err(err) => err.to_string(),
+                    _ => "no entries".to_string(),
+                };
+
+                self.begin()?;
+                self.quarantine(scope, &key, &data, &reason)?;
+                self.commit()?;
+
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(CachedEntries {
+            entries,
+            source,
+            fetched_at,
+        }))
+    }
+
+    // save upgraded entries over the old json, this is not a new fetch so
+    // the fetch time is kept
+    fn rewrite(
+        &self,
+        scope: &CacheScope,
+        key: &str,
+        entries: &[WordData],
+    ) -> Result<(), WordsError> {
+        self.conn.execute(
+            "UPDATE words SET data = ?1
+             WHERE word = ?2 AND provider = ?3 AND lang = ?4",
+            params![write_entries(entries)?, key, scope.provider, scope.lang],
+        )?;
+
+        Ok(())
+    }
+
+    // move a word that cant be read out of the words so it is fetched again
+    fn quarantine(
+        &self,
+        scope: &CacheScope,
+        key: &str,
+        data: &str,
+        reason: &str,
+    ) -> Result<(), WordsError> {
+        self.conn.execute(
+            "INSERT INTO quarantine
+                (word, provider, lang, data, reason, quarantined_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key,
+                scope.provider,
+                scope.lang,
+                data,
+                reason,
+                unix_time(SystemTime::now())
+            ],
+        )?;
+
+        self.conn.execute(
+            "DELETE FROM words
+             WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+            params![key, scope.provider, scope.lang],
+        )?;
+
+        Ok(())
+    }
+
+    /// write every word as a line of json and return how many were written,
+    /// words that cant be read are left out with a warning and counted
+    pub fn export<W: Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<ExportCount, WordsError> {
+        let rows = self
+            .conn
+            .prepare(
+                "SELECT word, provider, lang, source, fetched_at, data
+                 FROM words ORDER BY word, provider, lang",
+            )?
+            .query_map(params![], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?
+            .collect::<Result<
+                Vec<(String, String, String, String, i64, String)>,
+                _,
+            >>()?;
+
+        let mut count = ExportCount {
+            exported: 0,
+            skipped: 0,
+        };
+
+        for (word, provider, lang, source, fetched_at, data) in rows {
+            let entries = match read_entries(&data) {
+                Ok((entries, _)) => entries,
+                Err(err) => {
+                    eprintln!(
+                        "words_cli: skipping {} ({} {}): {}",
+                        word, provider, lang, err
+                    );
+
+                    count.skipped += 1;
+
+                    continue;
+                }
+            };
+
+            let aliases = self
+                .conn
+                .prepare(
+                    "SELECT alias FROM aliases
+                     WHERE word = ?1 AND provider = ?2 AND lang = ?3
+                     ORDER BY alias",
+                )?
+                .query_map(params![word, provider, lang], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+
+            let line = ExportedWord {
+                word,
+                provider,
+                lang,
+                source,
+                fetched_at,
+                aliases,
+                data: entries_to_value(&entries),
+            };
+
+            serde_json::to_writer(&mut writer, &line)?;
+
+            writer.write_all(b"\n").map_err(cache_error)?;
+
+            count.exported += 1;
+        }
+
+        writer.flush().map_err(cache_error)?;
+
+        Ok(count)
+    }
+
+    /// read words exported with `export` in to the cache, the merge mode
+    /// decides what happens when a word is already cached
+    pub fn import<R: BufRead>(
+        &self,
+        reader: R,
+        mode: &MergeMode,
+    ) -> Result<ImportCount, WordsError> {
+        let mut count = ImportCount {
+            imported: 0,
+            skipped: 0,
+        };
+
+        // all or nothing so a bad line does not leave half an import
+        self.begin()?;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| {
+                WordsError::BadInput(format!("cant read the import: {}", e))
+            })?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let exported: ExportedWord =
+                serde_json::from_str(&line).map_err(|e| {
+                    WordsError::Parse(format!("line {}: {}", index + 1, e))
+                })?;
+
+            let (entries, _) = entries_from_value(exported.data)?;
+
+            let key = match entries.first() {
+                Some(word_data) => cache_key(&word_data.word),
+                None => {
+                    return Err(WordsError::Parse(format!(
+                        "line {}: no entries for {}",
+                        index + 1,
+                        exported.word
+                    )))
+                }
+            };
+
+            let scope = CacheScope {
+                provider: exported.provider,
+                lang: exported.lang,
+            };
+
+            let existing: Option<i64> = self
+                .conn
+                .query_row(
+                    "SELECT fetched_at FROM words
+                     WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+                    params![key, scope.provider, scope.lang],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            let replace = match (mode, existing) {
+                (_, None) | (MergeMode::Overwrite, _) => true,
+                (MergeMode::Newest, Some(fetched_at)) => {
+                    exported.fetched_at > fetched_at
+                }
+                (MergeMode::Existing, Some(_)) => false,
+            };
+
+            if replace {
+                self.save(
+                    &scope,
+                    &entries,
+                    &exported.source,
+                    exported.fetched_at,
+                )?;
+
+                count.imported += 1;
+            } else {
+                count.skipped += 1;
+            }
+
+            // aliases that point somewhere else are only moved if the word was
+            let alias_sql = if replace {
+                "INSERT OR REPLACE INTO aliases (alias, provider, lang, word)
+                 VALUES (?1, ?2, ?3, ?4)"
+            } else {
+                "INSERT OR IGNORE INTO aliases (alias, provider, lang, word)
+                 VALUES (?1, ?2, ?3, ?4)"
+            };
+
+            for alias in exported.aliases {
+                let alias = cache_key(&alias);
+
+                if alias != key {
+                    self.conn.execute(
+                        alias_sql,
+                        params![alias, scope.provider, scope.lang, key],
+                    )?;
+                }
+            }
+        }
+
+        self.commit()?;
+
+        Ok(count)
+    }
+
+    /// upgrade the json for every word saved by an older version, words that
+    /// cant be read are quarantined, returns how many were upgraded and how
+    /// many were quarantined
+    pub fn migrate(&self) -> Result<(usize, usize), WordsError> {
+        let rows = self
+            .conn
+            .prepare("SELECT word, provider, lang, data FROM words")?
+            .query_map(params![], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<(String, String, String, String)>, _>>()?;
+
+        let mut upgraded = 0;
+        let mut quarantined = 0;
+
+        self.begin()?;
+
+        for (key, provider, lang, data) in rows {
+            let scope = CacheScope { provider, lang };
+
+            match read_entries(&data) {
+                Ok((entries, version)) if !entries.is_empty() => {
+                    if version < ENTRY_VERSION {
+                        self.rewrite(&scope, &key, &entries)?;
+
+                        upgraded += 1;
+                    }
+                }
+                // newer json is left for a newer words_cli
+                Err(WordsError::Cache(_)) => {}
+                result => {
+                    let reason = match result {
+                        Err(err) => err.to_string(),
+                        _ => "no entries".to_string(),
+                    };
+
+                    self.quarantine(&scope, &key, &data, &reason)?;
+
+                    quarantined += 1;
+                }
+            }
+        }
+
+        self.commit()?;
+
+        Ok((upgraded, quarantined))
+    }
+
+    /// remember the dictionary does not have a word and the message it gave
+    pub fn cache_not_found(
+        &self,
+        query: &str,
+        message: &str,
+        source: &str,
+    ) -> Result<(), WordsError> {
+        self.atomically(|| {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO missing
+                    (word, provider, lang, message, source, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    cache_key(query),
+                    self.scope.provider,
+                    self.scope.lang,
+                    message,
+                    source,
+                    unix_time(SystemTime::now())
+                ],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// return the message saved when a word was not found
+    pub fn get_not_found(
+        &self,
+        query: &str,
+    ) -> Result<Option<CachedNotFound>, WordsError> {
+        let not_found = self
+            .conn
+            .query_row(
+                "SELECT message, fetched_at FROM missing
+                 WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+                params![cache_key(query), self.scope.provider, self.scope.lang],
+                |row| {
+                    Ok(CachedNotFound {
+                        message: row.get(0)?,
+                        fetched_at: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(not_found)
+    }
+
+    /// if a word is cached, this does not count as a hit
+    pub fn contains(&self, query: &str) -> Result<bool, WordsError> {
+        let contains = self.conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM words
+                WHERE word = ?1 AND provider = ?2 AND lang = ?3
+            )",
+            params![self.resolve(query)?, self.scope.provider, self.scope.lang],
+            |row| row.get(0),
+        )?;
+
+        Ok(contains)
+    }
+
+    /// the words matching a glob pattern like `bas*`, in order
+    pub fn list(&self, pattern: &str) -> Result<Vec<CacheRow>, WordsError> {
+        let mut statement = self.conn.prepare(
+            "SELECT word, source, fetched_at, hits FROM words
+             WHERE word GLOB ?1 AND provider = ?2 AND lang = ?3
+             ORDER BY word",
+        )?;
+
+        let rows = statement
+            .query_map(
+                params![
+                    cache_key(pattern),
+                    self.scope.provider,
+                    self.scope.lang
+                ],
+                |row| {
+                    Ok(CacheRow {
+                        word: row.get(0)?,
+                        source: row.get(1)?,
+                        fetched_at: row.get(2)?,
+                        hits: row.get(3)?,
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(rows)
+    }
+
+    /// the json saved for a word, this does not count as a hit
+    pub fn get_raw(&self, word: &str) -> Result<Option<String>, WordsError> {
+        let data = self
+            .conn
+            .query_row(
+                "SELECT data FROM words
+                 WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+                params![
+                    self.resolve(word)?,
+                    self.scope.provider,
+                    self.scope.lang
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(data)
+    }
+
+    /// remove a word and its aliases, returns false if it was not cached
+    pub fn remove(&self, word: &str) -> Result<bool, WordsError> {
+        let scope = &self.scope;
+
+        let removed = self.conn.execute(
+            "DELETE FROM words WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+            params![self.resolve(word)?, scope.provider, scope.lang],
+        )? + self.conn.execute(
+            "DELETE FROM missing
+             WHERE word = ?1 AND provider = ?2 AND lang = ?3",
+            params![cache_key(word), scope.provider, scope.lang],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    /// remove every word fetched longer ago then `age` and return how many
+    /// were removed
+    pub fn prune(&self, age: Duration) -> Result<usize, WordsError> {
+        let age = age.as_secs().min(i64::MAX as u64) as i64;
+
+        let cutoff = unix_time(SystemTime::now()).saturating_sub(age);
+
+        let removed = self.conn.execute(
+            "DELETE FROM words WHERE fetched_at < ?1",
+            params![cutoff],
+        )? + self.conn.execute(
+            "DELETE FROM missing WHERE fetched_at < ?1",
+            params![cutoff],
+        )?;
+
+        Ok(removed)
+    }
+
+    /// remove every word and return how many were removed
+    pub fn clear(&self) -> Result<usize, WordsError> {
+        let removed = self.conn.execute("DELETE FROM words", params![])?
+            + self.conn.execute("DELETE FROM missing", params![])?;
+
+        self.conn.execute("DELETE FROM quarantine", params![])?;
+
+        // give the space back, the db would stay the same size otherwise
+        self.conn.execute_batch("VACUUM")?;
+
+        Ok(removed)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, WordsError> {
+        let count: i64 = self.conn.query_row(
+            "SELECT count(*) FROM words",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        let size: i64 = self.conn.query_row(
+            "SELECT page_count * page_size
+             FROM pragma_page_count(), pragma_page_size()",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        let word_at = |order: &str| {
+            self.conn
+                .query_row(
+                    &format!(
+                        "SELECT word, fetched_at FROM words
+                         ORDER BY fetched_at {}, word LIMIT 1",
+                        order
+                    ),
+                    params![],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+        };
+
+        let oldest = word_at("ASC")?;
+        let newest = word_at("DESC")?;
+
+        let top_hits = self
+            .conn
+            .prepare(
+                "SELECT word, hits FROM words WHERE hits > 0
+                 ORDER BY hits DESC, word LIMIT 10",
+            )?
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let quarantined: i64 = self.conn.query_row(
+            "SELECT count(*) FROM quarantine",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        Ok(CacheStats {
+            count: count as usize,
+            quarantined: quarantined as usize,
+            size: size as u64,
+            oldest,
+            newest,
+            top_hits,
+        })
+    }
+
+    /// start saving a lot of words at once, nothing is written until `commit`
+    /// so importing is a lot faster
+    pub fn begin(&self) -> Result<(), WordsError> {
+        // take the write lock now, waiting for it later can fail when
+        // another words_cli is writing
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+
+        Ok(())
+    }
+
+    pub fn commit(&self) -> Result<(), WordsError> {
+        self.conn.execute_batch("COMMIT")?;
+
+        Ok(())
+    }
+
+    // run the writes in a transaction and undo them all if one fails, when
+    // there is already a transaction, like while importing, a savepoint is
+    // used so only these writes are undone
+    fn atomically<T>(
+        &self,
+        write: impl FnOnce() -> Result<T, WordsError>,
+    ) -> Result<T, WordsError> {
+        let nested = !self.conn.is_autocommit();
+
+        if nested {
+            self.conn.execute_batch("SAVEPOINT atomically")?;
+        } else {
+            self.begin()?;
+        }
+
+        match write() {
+            Ok(value) => {
+                if nested {
+                    self.conn.execute_batch("RELEASE atomically")?;
+                } else {
+                    self.commit()?;
+                }
+
+                Ok(value)
+            }
+            Err(err) => {
+                let rollback = if nested {
+                    "ROLLBACK TO atomically; RELEASE atomically"
+                } else {
+                    "ROLLBACK"
+                };
+
+                // the write already failed, that error is the one to report
+                let _ = self.conn.execute_batch(rollback);
+
+                Err(err)
+            }
+        }
+    }
+
+    /// move the json files from the old cache directory in to the database
+    ///
+    /// the directory is renamed to `cache.migrated` after so it is only done
+    /// once and nothing is lost if something goes wrong, files that cant be
+    /// read are skipped and left there
+    fn migrate_file_cache(
+        &self,
+        file_cache_path: &Path,
+    ) -> Result<(), WordsError> {
+        let dir = match fs::read_dir(file_cache_path) {
+            Ok(dir) => dir,
+            // another words_cli already moved it
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(cache_error(err)),
+        };
+
+        self.begin()?;
+
+        for entry in dir {
+            let word_path = entry.map_err(cache_error)?.path();
+
+            let entries = match fs::read_to_string(&word_path)
+                .ok()
+                .and_then(|data| read_entries(&data).ok())
+                .map(|(entries, _)| entries)
+            {
+                Some(entries) if !entries.is_empty() => entries,
+                _ => continue,
+            };
+
+            let fetched_at = fs::metadata(&word_path)
+                .and_then(|meta| meta.modified())
+                .map_or(0, unix_time);
+
+            // the file cache only ever had dictionaryapi in english
+            self.save(
+                &CacheScope::default(),
+                &entries,
+                FILE_CACHE_SOURCE,
+                fetched_at,
+            )?;
+        }
+
+        self.commit()?;
+
+        let mut migrated_path = PathBuf::from(file_cache_path);
+
+        migrated_path.set_file_name("cache.migrated");
+
+        match fs::rename(file_cache_path, migrated_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(cache_error(err))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// export the cache to a file, or stdout if the path is `-`, the file is
+/// gzipped if it ends in `.gz`
+///
+/// the file is written next to where it goes and moved in to place when it is
+/// done so a failed export never leaves half a file
+pub fn export_to(cache: &Cache, path: &str) -> Result<ExportCount, WordsError> {
+    let export_error = |e: io::Error| {
+        WordsError::BadInput(format!("cant write {}: {}", path, e))
+    };
+
+    if path == "-" {
+        let stdout = io::stdout();
+
+        return cache.export(BufWriter::new(stdout.lock()));
+    }
+
+    let path = Path::new(path);
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut temp_file = NamedTempFile::new_in(dir).map_err(export_error)?;
+
+    let exported = if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut encoder = GzEncoder::new(
+            BufWriter::new(&mut temp_file),
+            Compression::default(),
+        );
+
+        let exported = cache.export(&mut encoder)?;
+
+        encoder.finish().map_err(export_error)?;
+
+        exported
+    } else {
+        cache.export(BufWriter::new(&mut temp_file))?
+    };
+
+    temp_file.persist(path).map_err(|e| export_error(e.error))?;
+
+    Ok(exported)
+}
+
+/// import a file made by `export_to`, or stdin if the path is `-`
+pub fn import_from(
+    cache: &Cache,
+    path: &str,
+    mode: &MergeMode,
+) -> Result<ImportCount, WordsError> {
+    let input: Box<dyn Read> = if path == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(path).map_err(|e| {
+            WordsError::BadInput(format!("cant open {}: {}", path, e))
+        })?)
+    };
+
+    let input: Box<dyn Read> =
+        if Path::new(path).extension().is_some_and(|ext| ext == "gz") {
+            Box::new(GzDecoder::new(input))
+        } else {
+            input
+        };
+
+    cache.import(BufReader::new(input), mode)
+}
+
+/// read an age like `30d`, a number with no unit is days
+///
+/// the units are `s`, `m`, `h`, `d` and `w`
+pub fn parse_age(age: &str) -> Result<Duration, WordsError> {
+    let age = age.trim();
+
+    let (number, unit) = match age.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => age.split_at(index),
+        None => (age, "d"),
+    };
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(WordsError::BadInput(format!(
+                "unknown unit in {}, use s, m, h, d or w",
+                age
+            )))
+        }
+    };
+
+    let number = number.parse::<u64>().map_err(|_| {
+        WordsError::BadInput(format!("{} is not an age like 30d", age))
+    })?;
+
+    let seconds = number.checked_mul(seconds).ok_or_else(|| {
+        WordsError::BadInput(format!("{} is too long ago", age))
+    })?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// the version of some saved json
+///
+/// - 0 is a single entry object, from before words had more then one entry
+/// - 1 is a list of entries
+/// - 2 and on are `{"version": 2, "entries": [...]}`
+fn entry_version(value: &Value) -> u64 {
+    match value {
+        Value::Array(_) => 1,
+        Value::Object(object) => {
+            object.get("version").and_then(Value::as_u64).unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+/// upgrade saved json one version at a time to `ENTRY_VERSION`
+///
+/// this works on the json and not WordData so each step only needs to know
+/// about the version before it
+fn upgrade_entries(mut value: Value) -> Result<Value, WordsError> {
+    let mut version = entry_version(&value);
+
+    if version > ENTRY_VERSION {
+        return Err(WordsError::Cache(format!(
+            "entry version {} is from a newer words_cli",
+            version
+        )));
+    }
+
+    while version < ENTRY_VERSION {
+        value = match version {
+            0 => Value::Array(vec![value]),
+            1 => json!({ "version": 2, "entries": value }),
+            _ => unreachable!("no upgrade from entry version {}", version),
+        };
+
+        version += 1;
+    }
+
+    Ok(value)
+}
+
+/// make WordData structs from saved json of any version and return the
+/// version it was saved as
+fn read_entries(data: &str) -> Result<(Vec<WordData>, u64), WordsError> {
+    entries_from_value(serde_json::from_str(data)?)
+}
+
+/// the same as `read_entries` for json that is already parsed
+fn entries_from_value(
+    value: Value,
+) -> Result<(Vec<WordData>, u64), WordsError> {
+    let version = entry_version(&value);
+
+    let entries = match upgrade_entries(value)? {
+        Value::Object(mut object) => object
+            .remove("entries")
+            .ok_or_else(|| WordsError::Parse("no entries".to_string()))?,
+        _ => return Err(WordsError::Parse("no entries".to_string())),
+    };
+
+    Ok((serde_json::from_value(entries)?, version))
+}
+
+/// the json to save entries as
+fn entries_to_value(entries: &[WordData]) -> Value {
+    json!({ "version": ENTRY_VERSION, "entries": entries })
+}
+
+fn write_entries(entries: &[WordData]) -> Result<String, WordsError> {
+    Ok(serde_json::to_string(&entries_to_value(entries))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::env;
+
+    use crate::test_utils::{fake_word_data, fake_word_strings, TempSetup};
+
+    fn set_data_home(root_path: &Path) -> PathBuf {
+        env::set_var("HOME", root_path.as_os_str());
+
+        let data_home = root_path.join(".local").join("share");
+
+        env::set_var("XDG_DATA_HOME", data_home.as_os_str());
+
+        data_home.join("words_cli")
+    }
+
+    #[test]
+    fn test_open_migrates_file_cache() {
+        use std::io::Write;
+
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let data_path = set_data_home(&root_path);
+
+        let cache_path = data_path.join("cache");
+
+        fs::create_dir_all(&cache_path).unwrap();
+
+        let words = fake_word_strings();
+
+        for (word, json_str) in &words {
+            let mut word_file =
+                fs::File::create(cache_path.join(word)).unwrap();
+
+            word_file.write_all(json_str.as_bytes()).unwrap();
+        }
+
+        fs::write(cache_path.join("broken"), "not json").unwrap();
+
+        let cache = Cache::open().unwrap();
+
+        for (word, json_str) in &words {
+            let entries = cache.get_from_cache(word).unwrap().unwrap().entries;
+
+            assert_eq!(entries.len(), 1, "did not read the old format");
+
+            assert_eq!(
+                serde_json::to_string(&entries[0]).unwrap(),
+                *json_str,
+                "did not migrate the file correctly"
+            )
+        }
+
+        assert!(!cache_path.exists(), "did not move the old cache");
+        assert!(
+            data_path.join("cache.migrated").join("broken").exists(),
+            "lost the file that could not be migrated"
+        );
+    }
+
+    #[test]
+    fn test_get_from_cache_missing() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        set_data_home(&root_path);
+
+        let cache = Cache::open().unwrap();
+
+        let word = cache.get_from_cache("test").unwrap();
+
+        assert!(word.is_none(), "some how the word test was made");
+    }
+
+    #[test]
+    fn test_cache_definition() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let data_path = set_data_home(&root_path);
+
+        let cache = Cache::open().unwrap();
+
+        for (word, json_str) in &fake_word_strings() {
+            let word_data: WordData = serde_json::from_str(json_str).unwrap();
+
+            cache.cache_definition(word, &[word_data], "test").unwrap();
+        }
+
+        assert!(data_path.join("cache.db").exists(), "did not make the db");
+
+        let (source, hits): (String, i64) = cache
+            .conn
+            .query_row(
+                "SELECT source, hits FROM words WHERE word = 'monster'",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+
+        assert_eq!(source, "test", "did not save the source");
+        assert_eq!(hits, 0);
+    }
+
+    #[test]
+    fn test_cache_definition_overwrites() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
+
+        cache.get_from_cache("test").unwrap();
+
+        let mut word_data = fake_word_data();
+
+        word_data.meanings[0].definitions[0].definition = "new".to_string();
+
+        cache
+            .cache_definition("test", &[word_data], "test")
+            .unwrap();
+
+        let entries = cache.get_from_cache("test").unwrap().unwrap().entries;
+
+        assert_eq!(entries[0].meanings[0].definitions[0].definition, "new");
+
+        let hits: i64 = cache
+            .conn
+            .query_row(
+                "SELECT hits FROM words WHERE word = 'test'",
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(hits, 2, "did not keep counting hits");
+    }
+
+    #[test]
+    fn test_peek_does_not_count_hits() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
+
+        let entries = cache.peek("Test").unwrap().unwrap().entries;
+
+        assert_eq!(entries.len(), 1);
+        assert!(cache.peek("missing").unwrap().is_none());
+
+        let hits: i64 = cache
+            .conn
+            .query_row(
+                "SELECT hits FROM words WHERE word = 'test'",
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(hits, 0, "counted a peek as a hit");
+    }
+
+    #[test]
+    fn test_cache_words_keeps_every_entry() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let mut second = fake_word_data();
+
+        second.meanings[0].definitions[0].definition = "second".to_string();
+
+        cache
+            .cache_definition("test", &[fake_word_data(), second], "test")
+            .unwrap();
+
+        let entries = cache.get_from_cache("test").unwrap().unwrap().entries;
+
+        assert_eq!(entries.len(), 2, "did not keep every entry");
+
+        assert_eq!(
+            entries[1].meanings[0].definitions[0].definition, "second",
+            "did not keep the order of the entries"
+        );
+    }
+
+    #[test]
+    fn test_cache_definition_no_entries() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        assert!(cache.cache_definition("test", &[], "test").is_err());
+    }
+
+    #[test]
+    fn test_cache_definition_rolls_back() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        // the word is saved before clearing missing fails
+        cache.conn.execute_batch("DROP TABLE missing").unwrap();
+
+        assert!(cache
+            .cache_definition("Test", &[fake_word_data()], "test")
+            .is_err());
+
+        assert!(cache.conn.is_autocommit(), "left the transaction open");
+        assert!(!cache.contains("test").unwrap(), "kept half a word");
+
+        // inside another transaction only the failed word is undone
+        cache.begin().unwrap();
+        cache
+            .save(&CacheScope::default(), &[fake_word_data()], "test", 0)
+            .unwrap();
+
+        assert!(cache
+            .cache_definition("Test", &[fake_word_data()], "test")
+            .is_err());
+
+        cache.commit().unwrap();
+
+        let fetched_at: i64 = cache
+            .conn
+            .query_row(
+                "SELECT fetched_at FROM words WHERE word = 'test'",
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(fetched_at, 0, "did not undo just the failed word");
+    }
+
+    #[test]
+    fn test_get_from_cache_is_stale() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let day = 24 * 60 * 60;
+
+        let fetched_at = unix_time(SystemTime::now()) - 2 * day;
+
+        cache
+            .save(
+                &CacheScope::default(),
+                &[fake_word_data()],
+                "test",
+                fetched_at,
+            )
+            .unwrap();
 
-use serde_json;
+        let cached = cache.get_from_cache("test").unwrap().unwrap();
 
-use crate::{dict_api::WordData, utils::get_data_path};
+        assert_eq!(cached.fetched_at, fetched_at, "did not keep the time");
 
-/// save a definition to the cache directory
-///
-/// this function just overwrites old definitions
-pub fn cache_definition(word_data: &WordData) -> Result<(), Box<dyn Error>> {
-    use std::io::Write;
+        assert!(cached.is_stale(Duration::from_secs(day as u64)));
+        assert!(!cached.is_stale(Duration::from_secs(3 * day as u64)));
+    }
+
+    #[test]
+    fn test_imported_words_are_never_stale() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let fetched_at = unix_time(SystemTime::now()) - 365 * 24 * 60 * 60;
 
-    let data_path = get_data_path()?;
+        cache
+            .save(
+                &CacheScope::default(),
+                &[fake_word_data()],
+                WIKTIONARY_SOURCE,
+                fetched_at,
+            )
+            .unwrap();
 
-    let cache_path = data_path.join("cache");
+        let cached = cache.get_from_cache("test").unwrap().unwrap();
 
-    // make the cache dir regardless, this should only get this far if either
-    // XDG_DATA_HOME or HOME exist, hopefully
-    if !cache_path.exists() {
-        fs::create_dir_all(&cache_path)?;
+        assert_eq!(cached.source, WIKTIONARY_SOURCE);
+        assert!(!cached.is_stale(Duration::from_secs(0)));
     }
 
-    let data_str = serde_json::to_string(word_data)?;
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30").unwrap(), Duration::from_secs(30 * 86400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+
+        assert!(parse_age("2y").is_err(), "took an unknown unit");
+        assert!(parse_age("d").is_err(), "took an age with no number");
 
-    let word_path = cache_path.join(&word_data.word);
+        match parse_age("99999999999999999w") {
+            Err(WordsError::BadInput(_)) => {}
+            other => panic!("took an age that overflows: {:?}", other),
+        }
+    }
 
-    // create truncates files if they exists
-    let mut word_file = fs::File::create(word_path)?;
+    #[test]
+    fn test_list_remove_and_prune() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
 
-    word_file.write_all(data_str.as_bytes())?;
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-    Ok(())
-}
+        let now = unix_time(SystemTime::now());
 
-/// return a definition from the cache if it exists else nothing if it doesn't
-pub fn get_from_cache(query: &str) -> Result<Option<WordData>, Box<dyn Error>> {
-    use std::io::Read;
+        for (word, age) in &[("bass", 0), ("base", 10), ("lead", 100)] {
+            let mut word_data = fake_word_data();
 
-    let data_path = get_data_path()?;
+            word_data.word = word.to_string();
 
-    let query_path = data_path.join("cache").join(query);
+            cache
+                .save(
+                    &CacheScope::default(),
+                    &[word_data],
+                    "test",
+                    now - age * 86400,
+                )
+                .unwrap();
+        }
 
-    if query_path.exists() {
-        let mut query_file = fs::File::open(query_path)?;
+        let words: Vec<String> = cache
+            .list("ba*")
+            .unwrap()
+            .into_iter()
+            .map(|row| row.word)
+            .collect();
 
-        let mut query_string = String::new();
+        assert_eq!(words, vec!["base", "bass"], "did not filter with the glob");
 
-        query_file.read_to_string(&mut query_string)?;
+        assert_eq!(cache.prune(Duration::from_secs(u64::MAX)).unwrap(), 0);
+        assert_eq!(cache.prune(Duration::from_secs(30 * 86400)).unwrap(), 1);
+        assert!(cache.get_raw("lead").unwrap().is_none(), "did not prune");
 
-        // make WordData struct from a json string
-        let word_data: WordData = serde_json::from_str(&query_string)?;
+        assert!(cache.remove("base").unwrap(), "did not remove the word");
+        assert!(!cache.remove("base").unwrap(), "removed the word twice");
 
-        Ok(Some(word_data))
-    } else {
-        Ok(None)
+        assert_eq!(cache.list("*").unwrap().len(), 1);
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.stats().unwrap().count, 0, "did not clear the cache");
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_stats() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
 
-    use std::env;
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        for (word, fetched_at) in &[("bass", 20), ("base", 10), ("lead", 30)] {
+            let mut word_data = fake_word_data();
+
+            word_data.word = word.to_string();
+
+            cache
+                .save(&CacheScope::default(), &[word_data], "test", *fetched_at)
+                .unwrap();
+        }
 
-    use crate::test_utils::{fake_word_strings, TempSetup};
+        cache.get_from_cache("lead").unwrap();
+        cache.get_from_cache("lead").unwrap();
+        cache.get_from_cache("bass").unwrap();
+
+        let stats = cache.stats().unwrap();
+
+        assert_eq!(stats.count, 3);
+        assert!(stats.size > 0, "did not get the size of the db");
+        assert_eq!(stats.oldest, Some(("base".to_string(), 10)));
+        assert_eq!(stats.newest, Some(("lead".to_string(), 30)));
+        assert_eq!(
+            stats.top_hits,
+            vec![("lead".to_string(), 2), ("bass".to_string(), 1)]
+        );
+    }
 
     #[test]
-    fn test_get_from_cache_files_exists() {
-        use std::io::Write;
+    fn test_cache_key() {
+        assert_eq!(cache_key(" Monster "), "monster");
+        assert_eq!(cache_key("STRASSE"), cache_key("straße"));
 
+        // an e with a combining accent and a single é are the same word
+        assert_eq!(cache_key("cafe\u{301}"), cache_key("caf\u{e9}"));
+
+        assert_eq!(cache_key("../../x"), "../../x", "changed the word");
+    }
+
+    #[test]
+    fn test_get_from_cache_any_case() {
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", root_path.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-        env::set_var("XDG_DATA_HOME", root_path.join(".local").join("share"));
+        let mut word_data = fake_word_data();
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+        word_data.word = "Monster".to_string();
 
-        fs::create_dir_all(&cache_path).unwrap();
+        cache
+            .cache_definition("Monster", &[word_data], "test")
+            .unwrap();
 
-        let words = fake_word_strings();
-        for (word, json_str) in &words {
-            let word_path = cache_path.join(word);
+        let entries = cache.get_from_cache("MONSTER").unwrap().unwrap().entries;
 
-            let mut word_file = fs::File::create(word_path).unwrap();
+        assert_eq!(entries[0].word, "Monster", "did not keep the headword");
 
-            word_file.write_all(json_str.as_bytes()).unwrap();
-        }
+        assert!(cache.get_from_cache("../../monster").unwrap().is_none());
+    }
 
-        for (word, json_str) in &words {
-            let cached_word = get_from_cache(&word).unwrap();
+    #[test]
+    fn test_get_from_cache_alias() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let mut word_data = fake_word_data();
+
+        word_data.word = "run".to_string();
+
+        cache.cache_definition("Ran", &[word_data], "test").unwrap();
+
+        let entries = cache.get_from_cache("ran").unwrap().unwrap().entries;
+
+        assert_eq!(entries[0].word, "run", "did not follow the alias");
+
+        assert!(cache.contains("RAN").unwrap());
+        assert!(!cache.contains("walk").unwrap());
+
+        assert!(cache.remove("run").unwrap());
+
+        let aliases: i64 = cache
+            .conn
+            .query_row("SELECT count(*) FROM aliases", params![], |row| {
+                row.get(0)
+            })
+            .unwrap();
+
+        assert_eq!(aliases, 0, "did not remove the alias with the word");
+    }
+
+    #[test]
+    fn test_open_normalizes_old_keys() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let db_path = root_path.join("cache.db");
+
+        {
+            let conn = Connection::open(&db_path).unwrap();
 
-            if let Some(word_data) = cached_word {
-                assert_eq!(
-                    serde_json::to_string(&word_data).unwrap(),
-                    *json_str,
-                    "did not read from file correctly"
+            conn.execute_batch(SCHEMA).unwrap();
+
+            for (word, fetched_at) in &[("Monster", 20), ("monster", 10)] {
+                let mut word_data = fake_word_data();
+
+                word_data.word = word.to_string();
+
+                conn.execute(
+                    "INSERT INTO words (word, data, source, fetched_at)
+                     VALUES (?1, ?2, 'test', ?3)",
+                    params![
+                        word,
+                        serde_json::to_string(&[word_data]).unwrap(),
+                        fetched_at
+                    ],
                 )
+                .unwrap();
             }
         }
 
-        // all words found
-        assert!(true);
+        let cache = Cache::open_at(&db_path).unwrap();
+
+        let rows = cache.list("*").unwrap();
+
+        assert_eq!(rows.len(), 1, "did not merge the words");
+        assert_eq!(rows[0].word, "monster");
+        assert_eq!(rows[0].fetched_at, 20, "did not keep the newest word");
     }
 
     #[test]
-    fn test_get_from_cache_dir_dose_not_exist() {
+    fn test_cache_not_found() {
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", root_path.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-        env::set_var("XDG_DATA_HOME", root_path.join(".local").join("share"));
+        cache
+            .cache_not_found("Tset", "No Definitions Found", "test")
+            .unwrap();
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+        let not_found = cache.get_not_found("tset").unwrap().unwrap();
 
-        fs::create_dir_all(&cache_path).unwrap();
+        assert_eq!(not_found.message, "No Definitions Found");
+        assert!(!not_found.is_stale(Duration::from_secs(60)));
+        assert!(not_found.is_stale(Duration::from_secs(0)));
 
-        let word = get_from_cache("test").unwrap();
+        let mut word_data = fake_word_data();
 
-        assert!(word.is_none(), "some how the word test was made");
+        word_data.word = "tset".to_string();
+
+        cache
+            .cache_definition("tset", &[word_data], "test")
+            .unwrap();
+
+        assert!(
+            cache.get_not_found("tset").unwrap().is_none(),
+            "still not found after it was found"
+        );
+
+        cache
+            .cache_not_found("missing", "No Definitions Found", "test")
+            .unwrap();
+
+        assert!(cache.remove("missing").unwrap(), "did not remove it");
+        assert!(cache.get_not_found("missing").unwrap().is_none());
     }
 
     #[test]
-    fn test_cache_words_cache_dir_exists() {
+    fn test_quarantine_keeps_the_scope() {
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", &root_path.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db"))
+            .unwrap()
+            .with_scope(scope("wordnet", "fr"));
+
+        cache
+            .conn
+            .execute(
+                "INSERT INTO words (word, provider, lang, data, source, fetched_at)
+                 VALUES ('test', 'wordnet', 'fr', 'not json', 'test', 0)",
+                params![],
+            )
+            .unwrap();
+
+        assert!(cache.get_from_cache("test").unwrap().is_none());
+
+        let quarantined: (String, String) = cache
+            .conn
+            .query_row(
+                "SELECT provider, lang FROM quarantine WHERE word = 'test'",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+
+        assert_eq!(quarantined, ("wordnet".into(), "fr".into()));
+    }
 
-        let data_home = root_path.join(".local").join("share");
+    #[test]
+    fn test_get_from_cache_quarantines_corrupt_words() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
 
-        env::set_var("XDG_DATA_HOME", data_home.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+        // half a word like a crash in the middle of writing would leave
+        cache
+            .conn
+            .execute(
+                "INSERT INTO words (word, data, source, fetched_at)
+                 VALUES ('test', '[{\"word\": \"te', 'test', 0)",
+                params![],
+            )
+            .unwrap();
 
-        fs::create_dir_all(&cache_path).unwrap();
+        assert!(
+            cache.get_from_cache("test").unwrap().is_none(),
+            "did not treat the corrupt word as missing"
+        );
 
-        let words = fake_word_strings();
+        assert_eq!(cache.stats().unwrap().quarantined, 1);
+        assert!(cache.get_raw("test").unwrap().is_none());
 
-        for (_, json_str) in &words {
-            let word_data = serde_json::from_str(json_str).unwrap();
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
 
-            let cache_result = cache_definition(&word_data);
+        assert!(cache.get_from_cache("test").unwrap().is_some());
+    }
 
-            if let Err(err) = cache_result {
-                eprintln!("{}", err);
-            } else {
-                assert!(true);
-            }
-        }
+    #[test]
+    fn test_open_at_moves_corrupt_database() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
 
-        for (word, _) in &words {
-            let word_path = cache_path.join(word);
-            assert!(word_path.exists(), "failed to make word file");
-        }
+        let db_path = root_path.join("cache.db");
+
+        fs::write(&db_path, "this is not a database at all, not even close")
+            .unwrap();
+
+        let cache = Cache::open_at(&db_path).unwrap();
+
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
+
+        let moved = fs::read_dir(&root_path)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("cache.db.corrupt-")
+            });
+
+        assert!(moved, "did not keep the corrupt database");
     }
 
     #[test]
-    fn test_cache_words_cache_dose_not_exists() {
+    fn test_cache_definition_in_parallel() {
+        use std::thread;
+
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", &root_path.as_os_str());
+        let db_path = root_path.join("cache.db");
 
-        let data_home = root_path.join(".local").join("share");
+        Cache::open_at(&db_path).unwrap();
 
-        env::set_var("XDG_DATA_HOME", data_home.as_os_str());
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let db_path = db_path.clone();
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+                thread::spawn(move || {
+                    let cache = Cache::open_at(&db_path).unwrap();
 
-        let words = fake_word_strings();
+                    for index in 0..25 {
+                        let mut word_data = fake_word_data();
 
-        for (_, json_str) in &words {
-            let word_data = serde_json::from_str(json_str).unwrap();
+                        word_data.word = format!("word{}-{}", writer, index);
 
-            let cache_result = cache_definition(&word_data);
+                        cache
+                            .cache_definition(
+                                &word_data.word.clone(),
+                                &[word_data],
+                                "test",
+                            )
+                            .unwrap();
 
-            if let Err(err) = cache_result {
-                eprintln!("{}", err);
-            } else {
-                assert!(true);
-            }
+                        cache.get_from_cache("word0-0").unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for writer in writers {
+            writer.join().unwrap();
         }
 
-        for (word, _) in &words {
-            let word_path = cache_path.join(word);
-            assert!(word_path.exists(), "failed to make word file");
+        let cache = Cache::open_at(&db_path).unwrap();
+
+        assert_eq!(cache.stats().unwrap().count, 100, "lost some words");
+    }
+
+    // json saved by each version of the cache, the first cache saved a single
+    // entry without antonyms or an etymology
+    const ENTRY_V0: &str = r#"{"word":"test","phonetics":[{"text":"test","audio":"test"}],"meanings":[{"partOfSpeech":"noun","definitions":[{"definition":"a test","example":null,"synonyms":null}]}]}"#;
+
+    const ENTRY_V1: &str = r#"[{"word":"test","phonetics":[],"meanings":[{"partOfSpeech":"noun","definitions":[{"definition":"a test","example":null,"synonyms":null,"antonyms":["pass"]}]}],"etymology":"from a test"},{"word":"test","phonetics":[],"meanings":[{"partOfSpeech":"verb","definitions":[{"definition":"to test","example":null,"synonyms":null}]}]}]"#;
+
+    const ENTRY_V2: &str = r#"{"version":2,"entries":[{"word":"test","phonetics":[],"meanings":[{"partOfSpeech":"noun","definitions":[{"definition":"a test","example":null,"synonyms":null}]}]}]}"#;
+
+    #[test]
+    fn test_read_entries_v0() {
+        let (entries, version) = read_entries(ENTRY_V0).unwrap();
+
+        assert_eq!(version, 0);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].meanings[0].definitions[0].definition, "a test");
+        assert!(entries[0].etymology.is_none());
+    }
+
+    #[test]
+    fn test_read_entries_v1() {
+        let (entries, version) = read_entries(ENTRY_V1).unwrap();
+
+        assert_eq!(version, 1);
+        assert_eq!(entries.len(), 2, "did not keep every entry");
+        assert_eq!(entries[0].etymology.as_deref(), Some("from a test"));
+        assert_eq!(
+            entries[0].meanings[0].definitions[0].antonyms,
+            Some(vec!["pass".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_read_entries_v2() {
+        let (entries, version) = read_entries(ENTRY_V2).unwrap();
+
+        assert_eq!(version, ENTRY_VERSION);
+        assert_eq!(entries[0].meanings[0].partOfSpeech, "noun");
+
+        let (_, version) =
+            read_entries(&write_entries(&entries).unwrap()).unwrap();
+
+        assert_eq!(version, ENTRY_VERSION, "did not save the version");
+    }
+
+    #[test]
+    fn test_read_entries_newer_version() {
+        let err = read_entries(r#"{"version":99,"entries":[]}"#).unwrap_err();
+
+        assert!(matches!(err, WordsError::Cache(_)), "got {:?}", err);
+    }
+
+    // save raw json for a word like an older words_cli would have
+    fn insert_raw(cache: &Cache, word: &str, data: &str) {
+        cache
+            .conn
+            .execute(
+                "INSERT INTO words (word, data, source, fetched_at)
+                 VALUES (?1, ?2, 'test', 10)",
+                params![word, data],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_from_cache_upgrades_entries() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        insert_raw(&cache, "test", ENTRY_V0);
+
+        let cached = cache.get_from_cache("test").unwrap().unwrap();
+
+        assert_eq!(cached.fetched_at, 10, "changed the fetch time");
+
+        let (_, version) =
+            read_entries(&cache.get_raw("test").unwrap().unwrap()).unwrap();
+
+        assert_eq!(version, ENTRY_VERSION, "did not save the upgrade");
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        insert_raw(&cache, "zero", ENTRY_V0);
+        insert_raw(&cache, "one", ENTRY_V1);
+        insert_raw(&cache, "two", ENTRY_V2);
+        insert_raw(&cache, "broken", "{");
+        insert_raw(&cache, "newer", r#"{"version":99,"entries":[]}"#);
+
+        assert_eq!(cache.migrate().unwrap(), (2, 1));
+
+        for word in &["zero", "one", "two"] {
+            let (_, version) =
+                read_entries(&cache.get_raw(word).unwrap().unwrap()).unwrap();
+
+            assert_eq!(version, ENTRY_VERSION, "did not upgrade {}", word);
         }
+
+        assert!(cache.get_raw("broken").unwrap().is_none());
+        assert!(cache.get_raw("newer").unwrap().is_some(), "lost newer json");
+
+        assert_eq!(cache.migrate().unwrap(), (0, 0), "upgraded twice");
     }
 
     #[test]
-    fn test_cache_words() {
+    fn test_export_and_import() {
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", &root_path.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-        let data_home = root_path.join(".local").join("share");
+        let mut word_data = fake_word_data();
 
-        env::set_var("XDG_DATA_HOME", data_home.as_os_str());
+        word_data.word = "run".to_string();
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+        cache.cache_definition("ran", &[word_data], "test").unwrap();
 
-        let words = fake_word_strings();
+        insert_raw(&cache, "test", ENTRY_V0);
+        insert_raw(&cache, "broken", "{");
 
-        for (_, json_str) in &words {
-            let word_data = serde_json::from_str(json_str).unwrap();
+        let mut exported = Vec::new();
 
-            let cache_result = cache_definition(&word_data);
+        assert_eq!(
+            cache.export(&mut exported).unwrap(),
+            ExportCount {
+                exported: 2,
+                skipped: 1
+            },
+            "exported junk or did not count it"
+        );
 
-            if let Err(err) = cache_result {
-                eprintln!("{}", err);
-            } else {
-                assert!(true);
+        let other = Cache::open_at(&root_path.join("other.db")).unwrap();
+
+        let count = other
+            .import(exported.as_slice(), &MergeMode::Newest)
+            .unwrap();
+
+        assert_eq!(
+            count,
+            ImportCount {
+                imported: 2,
+                skipped: 0
             }
-        }
+        );
 
-        for (word, _) in &words {
-            let word_path = cache_path.join(word);
-            assert!(
-                word_path.exists(),
-                "cache_definition failed to make word file"
-            );
-        }
+        let cached = other.get_from_cache("ran").unwrap().unwrap();
+
+        assert_eq!(cached.entries[0].word, "run", "did not keep the alias");
+
+        let rows = other.list("*").unwrap();
+
+        assert_eq!(rows[1].word, "test");
+        assert_eq!(rows[1].fetched_at, 10, "did not keep the fetch time");
+        assert_eq!(rows[1].source, "test", "did not keep the source");
     }
 
     #[test]
-    fn test_cache_words_overwites_files() {
-        use std::io::Write;
+    fn test_import_merge_modes() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let line = |definition: &str, fetched_at: i64| {
+            let mut word_data = fake_word_data();
+
+            word_data.meanings[0].definitions[0].definition =
+                definition.to_string();
+
+            format!(
+                "{}\n",
+                serde_json::to_string(&ExportedWord {
+                    word: "test".to_string(),
+                    provider: default_provider(),
+                    lang: default_lang(),
+                    source: "import".to_string(),
+                    fetched_at,
+                    aliases: Vec::new(),
+                    data: entries_to_value(&[word_data]),
+                })
+                .unwrap()
+            )
+        };
+
+        let definition = |cache: &Cache| {
+            let entries =
+                cache.get_from_cache("test").unwrap().unwrap().entries;
+
+            entries[0].meanings[0].definitions[0].definition.clone()
+        };
+
+        cache
+            .import(line("first", 20).as_bytes(), &MergeMode::Newest)
+            .unwrap();
+
+        cache
+            .import(line("older", 10).as_bytes(), &MergeMode::Newest)
+            .unwrap();
 
+        assert_eq!(definition(&cache), "first", "replaced a newer word");
+
+        cache
+            .import(line("newer", 30).as_bytes(), &MergeMode::Existing)
+            .unwrap();
+
+        assert_eq!(definition(&cache), "first", "replaced the cached word");
+
+        cache
+            .import(line("older", 10).as_bytes(), &MergeMode::Overwrite)
+            .unwrap();
+
+        assert_eq!(definition(&cache), "older", "did not overwrite the word");
+
+        assert!(
+            cache.import("{".as_bytes(), &MergeMode::Newest).is_err(),
+            "imported a broken line"
+        );
+    }
+
+    #[test]
+    fn test_export_to_gzip() {
         let mut temp = TempSetup::default();
         let root_path = temp.setup();
 
-        env::set_var("HOME", &root_path.as_os_str());
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
 
-        let data_home = root_path.join(".local").join("share");
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
 
-        env::set_var("XDG_DATA_HOME", data_home.as_os_str());
+        let path = root_path.join("cache.jsonl.gz");
 
-        let cache_path = root_path
-            .join(".local")
-            .join("share")
-            .join("words_cli")
-            .join("cache");
+        let path = path.to_str().unwrap();
 
-        fs::create_dir_all(&cache_path).unwrap();
+        assert_eq!(export_to(&cache, path).unwrap().exported, 1);
 
-        let words = fake_word_strings();
+        let other = Cache::open_at(&root_path.join("other.db")).unwrap();
 
-        for (word, json_str) in &words {
-            let word_path = cache_path.join(word);
+        let count = import_from(&other, path, &MergeMode::Newest).unwrap();
 
-            let mut word_file = fs::File::create(word_path).unwrap();
+        assert_eq!(count.imported, 1);
+        assert!(other.get_from_cache("test").unwrap().is_some());
+    }
 
-            word_file.write_all(json_str.as_bytes()).unwrap();
+    #[test]
+    fn test_search() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let words = [
+            ("bass", "A low spectrum of sound tones."),
+            ("treble", "The high range of sound."),
+            ("perch", "A fish that lives in rivers."),
+        ];
+
+        for (word, definition) in &words {
+            let mut word_data = fake_word_data();
+
+            word_data.word = word.to_string();
+            word_data.meanings[0].definitions[0].definition =
+                definition.to_string();
+
+            cache.cache_definition(word, &[word_data], "test").unwrap();
         }
 
-        for (_, json_str) in &words {
-            let word_data = serde_json::from_str(json_str).unwrap();
+        let results = cache.search("low sounds", 10, ("[", "]")).unwrap();
 
-            let cache_result = cache_definition(&word_data);
+        let found: Vec<&str> =
+            results.iter().map(|result| result.word.as_str()).collect();
 
-            if let Err(err) = cache_result {
-                eprintln!("{}", err);
-            } else {
-                assert!(true);
-            }
+        assert_eq!(found, vec!["bass", "treble"], "did not rank the matches");
+        assert!(
+            results[0].snippet.contains("[low]"),
+            "did not highlight the match: {}",
+            results[0].snippet
+        );
+
+        assert_eq!(cache.search("sound", 1, ("", "")).unwrap().len(), 1);
+
+        // fts syntax is just searched for
+        assert!(cache.search("fish\" OR NEAR(", 10, ("", "")).is_ok());
+        assert!(cache.search("  ", 10, ("", "")).is_err());
+
+        cache.remove("perch").unwrap();
+
+        assert!(
+            cache.search("fish", 10, ("", "")).unwrap().is_empty(),
+            "removed words are still searched"
+        );
+    }
+
+    #[test]
+    fn test_open_builds_search_index() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let db_path = root_path.join("cache.db");
+
+        {
+            let cache = Cache::open_at(&db_path).unwrap();
+
+            insert_raw(&cache, "test", ENTRY_V1);
+
+            cache.conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        }
+
+        let cache = Cache::open_at(&db_path).unwrap();
+
+        let results = cache.search("a test", 10, ("", "")).unwrap();
+
+        assert_eq!(results.len(), 1, "did not index the old words");
+        assert_eq!(results[0].word, "test");
+    }
+
+    fn scope(provider: &str, lang: &str) -> CacheScope {
+        CacheScope {
+            provider: provider.to_string(),
+            lang: lang.to_string(),
         }
+    }
+
+    #[test]
+    fn test_cache_scopes() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let db_path = root_path.join("cache.db");
+
+        let english = Cache::open_at(&db_path).unwrap();
+        let french = Cache::open_at(&db_path)
+            .unwrap()
+            .with_scope(scope("dictionaryapi", "fr"));
+        let wordnet = Cache::open_at(&db_path)
+            .unwrap()
+            .with_scope(scope("wordnet", "en"));
+
+        let chat = |definition: &str| {
+            let mut word_data = fake_word_data();
+
+            word_data.word = "chat".to_string();
+            word_data.meanings[0].definitions[0].definition =
+                definition.to_string();
+
+            word_data
+        };
+
+        english
+            .cache_definition("Chats", &[chat("a talk")], "test")
+            .unwrap();
+
+        french
+            .cache_definition("chat", &[chat("un felin")], "test")
+            .unwrap();
+
+        let definition = |cache: &Cache, query: &str| {
+            cache.get_from_cache(query).unwrap().map(|cached| {
+                cached.entries[0].meanings[0].definitions[0]
+                    .definition
+                    .clone()
+            })
+        };
+
+        assert_eq!(definition(&english, "chat").as_deref(), Some("a talk"));
+        assert_eq!(definition(&french, "chat").as_deref(), Some("un felin"));
+        assert_eq!(definition(&wordnet, "chat"), None, "used another provider");
+
+        // aliases and missing words are kept apart too
+        assert_eq!(definition(&english, "chats").as_deref(), Some("a talk"));
+        assert_eq!(definition(&french, "chats"), None, "used an english alias");
+
+        wordnet
+            .cache_not_found("chat", "No Definitions Found", "wordnet")
+            .unwrap();
+
+        assert!(wordnet.get_not_found("chat").unwrap().is_some());
+        assert!(english.get_not_found("chat").unwrap().is_none());
+
+        assert_eq!(english.search("felin", 10, ("", "")).unwrap().len(), 0);
+        assert_eq!(french.search("felin", 10, ("", "")).unwrap().len(), 1);
+
+        assert!(french.remove("chat").unwrap());
+        assert!(english.contains("chat").unwrap(), "removed every language");
+    }
 
-        for (word, _) in &words {
-            let word_path = cache_path.join(word);
-            assert!(word_path.exists(), "failed to make word file");
+    #[test]
+    fn test_open_adds_scopes() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let db_path = root_path.join("cache.db");
+
+        // the tables before there were scopes
+        {
+            let conn = Connection::open(&db_path).unwrap();
+
+            conn.execute_batch(
+                "CREATE TABLE words (
+                    word TEXT PRIMARY KEY NOT NULL,
+                    data TEXT NOT NULL,
+                    source TEXT NOT NULL,
+                    fetched_at INTEGER NOT NULL,
+                    hits INTEGER NOT NULL DEFAULT 0
+                );
+                CREATE TABLE aliases (
+                    alias TEXT PRIMARY KEY NOT NULL,
+                    word TEXT NOT NULL REFERENCES words (word)
+                        ON DELETE CASCADE
+                );
+                CREATE TABLE missing (
+                    word TEXT PRIMARY KEY NOT NULL,
+                    message TEXT NOT NULL,
+                    source TEXT NOT NULL,
+                    fetched_at INTEGER NOT NULL
+                );
+                CREATE VIRTUAL TABLE search USING fts5 (
+                    word UNINDEXED,
+                    headword,
+                    definitions,
+                    examples,
+                    synonyms
+                );
+                CREATE TRIGGER words_search_delete AFTER DELETE ON words
+                BEGIN
+                    DELETE FROM search WHERE word = old.word;
+                END;
+                CREATE TRIGGER words_search_rename AFTER UPDATE OF word ON words
+                BEGIN
+                    UPDATE search SET word = new.word WHERE word = old.word;
+                END;
+                CREATE TABLE quarantine (
+                    word TEXT NOT NULL,
+                    data TEXT NOT NULL,
+                    reason TEXT NOT NULL,
+                    quarantined_at INTEGER NOT NULL
+                );
+                INSERT INTO quarantine VALUES ('bad', '[', 'bad json', 10);
+                PRAGMA user_version = 2;",
+            )
+            .unwrap();
+
+            for (word, source) in
+                &[("test", "dictionaryapi"), ("tset", "wordnet")]
+            {
+                let mut word_data = fake_word_data();
+
+                word_data.word = word.to_string();
+
+                conn.execute(
+                    "INSERT INTO words (word, data, source, fetched_at, hits)
+                     VALUES (?1, ?2, ?3, 10, 4)",
+                    params![word, write_entries(&[word_data]).unwrap(), source],
+                )
+                .unwrap();
+            }
+
+            conn.execute_batch(
+                "INSERT INTO aliases (alias, word) VALUES ('tests', 'test');
+                 INSERT INTO missing (word, message, source, fetched_at)
+                 VALUES ('zzz', 'No Definitions Found', 'wordnet', 10);",
+            )
+            .unwrap();
         }
+
+        let cache = Cache::open_at(&db_path).unwrap();
+
+        assert!(cache.has_scopes().unwrap(), "did not add the scopes");
+
+        let rows = cache.list("*").unwrap();
+
+        assert_eq!(rows.len(), 1, "did not keep the providers apart");
+        assert_eq!((rows[0].word.as_str(), rows[0].hits), ("test", 4));
+
+        assert!(cache.contains("tests").unwrap(), "lost the alias");
+        assert_eq!(cache.search("test", 10, ("", "")).unwrap().len(), 1);
+
+        let wordnet = cache.with_scope(scope("wordnet", "en"));
+
+        assert!(wordnet.contains("tset").unwrap());
+        assert!(wordnet.get_not_found("zzz").unwrap().is_some());
+
+        let quarantined: (String, String, String) = wordnet
+            .conn
+            .query_row(
+                "SELECT word, provider, lang FROM quarantine",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+
+        assert_eq!(
+            quarantined,
+            ("bad".into(), "dictionaryapi".into(), "en".into()),
+            "did not add the scope to the quarantine"
+        );
     }
 }
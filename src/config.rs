@@ -24,14 +24,60 @@
 //! example = "\x1b[3m"
 //! synonyms_title = "\x1b[4m"
 //! synonyms = "\x1b[0m"
+//! antonyms_title = "\x1b[4m"
+//! antonyms = "\x1b[0m"
+//!
+//!
+//! # where to get definitions from
+//! [dictionary]
+//! # `provider` is the name of the provider to get definitions from
+//! provider = "dictionaryapi"
+//! # `wordnet_path` is the WordNet database directory for the wordnet provider
+//! wordnet_path = "/usr/share/wordnet"
+//! # `stardict_path` is the directory with the bundles for the stardict provider
+//! stardict_path = "/usr/share/stardict/dic"
+//! # `dictd_host` and `dictd_port` are where the dict server is
+//! dictd_host = "localhost"
+//! dictd_port = 2628
+//! # `database` is the dict database or stardict bookname to look in, `*` for
+//! # all of them
+//! database = "*"
+//! # `strategy` is the dict match strategy used to suggest words
+//! strategy = "."
+//! # `retries` is how many times to retry when dictionaryapi is busy
+//! retries = 3
+//!
+//!
+//! # how to talk to dictionaryapi or a mirror of it
+//! [api]
+//! # `base_url` is the entries endpoint, the language and word are added to it
+//! base_url = "https://api.dictionaryapi.dev/api/v2/entries"
+//! # `lang` is the language code of the words to look up
+//! lang = "en"
+//! # `timeout` is how many seconds to wait for a response
+//! timeout = 10
+//! # `user_agent` is sent with every request
+//! user_agent = "words_cli/0.1.0"
+//! # `proxy` is a http or socks proxy to send requests through
+//! proxy = "http://localhost:8080"
+//!
+//!
+//! # how long to keep definitions
+//! [cache]
+//! # `ttl` is how many days a definition is used before fetching it again
+//! ttl = 30
+//! # `not_found_ttl` is how many hours to remember a word was not found
+//! not_found_ttl = 24
 //! ```
-use std::{error::Error, fs, path::PathBuf};
+use std::{fs, path::PathBuf, time::Duration};
 
 use serde::Deserialize;
-use toml;
 
 use crate::{
+    cache::CacheOptions,
+    error::WordsError,
     formatter::{FormatterConfig, FormatterStyle},
+    provider::ProviderConfig,
     utils::get_user_config_path,
 };
 
@@ -52,6 +98,10 @@ use crate::{
 ///
 /// default synonyms: "\x1b[0m"
 ///
+/// default antonyms_title: "\x1b[4m"
+///
+/// default antonyms: "\x1b[0m"
+///
 /// default reset: "\x1b[0m"
 #[derive(Deserialize, Debug)]
 pub struct FormatStyle {
@@ -62,6 +112,8 @@ pub struct FormatStyle {
     pub example: Option<String>,
     pub synonyms_title: Option<String>,
     pub synonyms: Option<String>,
+    pub antonyms_title: Option<String>,
+    pub antonyms: Option<String>,
     // this will more or less be the same i guess
     pub reset: Option<String>,
 }
@@ -87,6 +139,8 @@ pub struct FormatStyle {
 /// default: 5,
 #[derive(Deserialize, Debug)]
 pub struct FormatConfig {
+    // read from the config but not used yet
+    #[allow(dead_code)]
     pub formating: Option<bool>,
     pub columns: Option<usize>,
     pub indent_by: Option<usize>,
@@ -94,10 +148,103 @@ pub struct FormatConfig {
     pub synonym_limit: Option<usize>,
 }
 
+/// where to get definitions from
+///
+///
+/// `provider` is the name of the provider to get definitions from
+///
+/// default: "dictionaryapi"
+///
+/// `wordnet_path` is the WordNet database directory for the wordnet provider
+///
+/// default: WNSEARCHDIR or "/usr/share/wordnet"
+///
+/// `stardict_path` is the directory with the bundles for the stardict provider
+///
+/// default: "~/.stardict/dic" or "/usr/share/stardict/dic"
+///
+/// `dictd_host` and `dictd_port` are where the dict server is
+///
+/// default: "localhost" and 2628
+///
+/// `database` is the dict database or stardict bookname to look in, `*` for
+/// all of them
+///
+/// default: "*"
+///
+/// `strategy` is the dict match strategy used to suggest words
+///
+/// default: "."
+///
+/// `retries` is how many times to retry when dictionaryapi is busy
+///
+/// default: 3
+#[derive(Deserialize, Debug)]
+pub struct DictionaryConfig {
+    pub provider: Option<String>,
+    pub wordnet_path: Option<String>,
+    pub stardict_path: Option<String>,
+    pub dictd_host: Option<String>,
+    pub dictd_port: Option<u16>,
+    pub database: Option<String>,
+    pub strategy: Option<String>,
+    pub retries: Option<u32>,
+}
+
+/// how to talk to dictionaryapi or a mirror of it
+///
+///
+/// `base_url` is the entries endpoint, the language and word are added to it
+///
+/// default: "https://api.dictionaryapi.dev/api/v2/entries"
+///
+/// `lang` is the language code of the words to look up
+///
+/// default: "en"
+///
+/// `timeout` is how many seconds to wait for a response
+///
+/// default: 10
+///
+/// `user_agent` is sent with every request
+///
+/// default: "words_cli/<version>"
+///
+/// `proxy` is a http or socks proxy to send requests through
+///
+/// default: none
+#[derive(Deserialize, Debug)]
+pub struct ApiConfig {
+    pub base_url: Option<String>,
+    pub lang: Option<String>,
+    pub timeout: Option<u64>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+}
+
+/// how long to keep definitions
+///
+///
+/// `ttl` is how many days a definition is used before fetching it again
+///
+/// default: 30
+///
+/// `not_found_ttl` is how many hours to remember a word was not found
+///
+/// default: 24
+#[derive(Deserialize, Debug)]
+pub struct CacheConfig {
+    pub ttl: Option<u64>,
+    pub not_found_ttl: Option<u64>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct WordsConfig {
     pub format_config: Option<FormatConfig>,
     pub format_style: Option<FormatStyle>,
+    pub dictionary: Option<DictionaryConfig>,
+    pub api: Option<ApiConfig>,
+    pub cache: Option<CacheConfig>,
 }
 
 impl WordsConfig {
@@ -133,6 +280,14 @@ impl WordsConfig {
                 format_style.synonyms = synonyms;
             }
 
+            if let Some(antonyms_title) = style.antonyms_title.take() {
+                format_style.antonyms_title = antonyms_title;
+            }
+
+            if let Some(antonyms) = style.antonyms.take() {
+                format_style.antonyms = antonyms;
+            }
+
             if let Some(reset) = style.reset.take() {
                 format_style.reset = reset;
             }
@@ -169,6 +324,7 @@ impl WordsConfig {
         }
     }
 
+    #[allow(clippy::manual_unwrap_or_default)]
     pub fn resolve_config(&mut self) -> FormatterConfig {
         let mut config = if let Some(conf) = self.resolve_formatter_config() {
             conf
@@ -182,11 +338,94 @@ impl WordsConfig {
 
         config
     }
+
+    pub fn resolve_provider_config(&mut self) -> ProviderConfig {
+        let mut provider_config = ProviderConfig::default();
+
+        if let Some(dictionary) = self.dictionary.as_mut() {
+            if let Some(provider) = dictionary.provider.take() {
+                provider_config.provider = provider;
+            }
+
+            if let Some(wordnet_path) = dictionary.wordnet_path.take() {
+                provider_config.wordnet_path =
+                    Some(PathBuf::from(wordnet_path));
+            }
+
+            if let Some(stardict_path) = dictionary.stardict_path.take() {
+                provider_config.stardict_path =
+                    Some(PathBuf::from(stardict_path));
+            }
+
+            if let Some(dictd_host) = dictionary.dictd_host.take() {
+                provider_config.dictd_host = dictd_host;
+            }
+
+            if let Some(dictd_port) = dictionary.dictd_port {
+                provider_config.dictd_port = dictd_port;
+            }
+
+            if let Some(database) = dictionary.database.take() {
+                provider_config.database = database;
+            }
+
+            if let Some(strategy) = dictionary.strategy.take() {
+                provider_config.strategy = strategy;
+            }
+
+            if let Some(retries) = dictionary.retries {
+                provider_config.retries = retries;
+            }
+        }
+
+        if let Some(api) = self.api.as_mut() {
+            let request_opts = &mut provider_config.request_opts;
+
+            if let Some(base_url) = api.base_url.take() {
+                request_opts.url = base_url;
+            }
+
+            if let Some(lang) = api.lang.take() {
+                request_opts.lang = lang;
+            }
+
+            if let Some(timeout) = api.timeout {
+                request_opts.timeout = Duration::from_secs(timeout);
+            }
+
+            if let Some(user_agent) = api.user_agent.take() {
+                request_opts.user_agent = user_agent;
+            }
+
+            if let Some(proxy) = api.proxy.take() {
+                request_opts.proxy = Some(proxy);
+            }
+        }
+
+        provider_config
+    }
+
+    pub fn resolve_cache_options(&mut self) -> CacheOptions {
+        let mut cache_options = CacheOptions::default();
+
+        if let Some(cache) = self.cache.as_mut() {
+            if let Some(ttl) = cache.ttl {
+                cache_options.ttl = Duration::from_secs(ttl * 24 * 60 * 60);
+            }
+
+            if let Some(not_found_ttl) = cache.not_found_ttl {
+                cache_options.not_found_ttl =
+                    Duration::from_secs(not_found_ttl * 60 * 60);
+            }
+        }
+
+        cache_options
+    }
 }
 
 pub fn get_user_config(
     optional_path: Option<&String>,
-) -> Result<Option<WordsConfig>, Box<dyn Error>> {
+) -> Result<Option<WordsConfig>, WordsError> {
     use std::io::Read;
 
     let config_toml_path = if let Some(optional_path) = optional_path {
@@ -201,11 +440,22 @@ pub fn get_user_config(
         return Ok(None);
     }
 
-    let mut user_config_file = fs::File::open(config_toml_path)?;
+    let read_error = |e: std::io::Error| {
+        WordsError::Config(format!(
+            "cant read {}: {}",
+            config_toml_path.display(),
+            e
+        ))
+    };
+
+    let mut user_config_file =
+        fs::File::open(&config_toml_path).map_err(read_error)?;
 
     let mut buf = String::new();
 
-    user_config_file.read_to_string(&mut buf)?;
+    user_config_file
+        .read_to_string(&mut buf)
+        .map_err(read_error)?;
 
     let user_config: WordsConfig = toml::from_str(&buf)?;
 
@@ -213,6 +463,7 @@ pub fn get_user_config(
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants, clippy::needless_borrow)]
 mod test {
     use super::*;
 
@@ -237,6 +488,9 @@ mod test {
             example = ""
             synonyms_title = ""
             synonyms = ""
+
+            [dictionary]
+            provider = "test"
             "#
         .to_string()
     }
@@ -251,12 +505,17 @@ mod test {
             example: Some("test".to_string()),
             synonyms_title: Some("test".to_string()),
             synonyms: Some("test".to_string()),
+            antonyms_title: None,
+            antonyms: None,
             reset: Some("test".to_string()),
         };
 
         let mut word_config = WordsConfig {
             format_style: Some(style_config),
             format_config: None,
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_style = word_config.resolve_style().unwrap();
@@ -299,6 +558,9 @@ mod test {
         let mut word_config = WordsConfig {
             format_style: None,
             format_config: None,
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         match word_config.resolve_style() {
@@ -323,6 +585,9 @@ mod test {
         let mut word_config = WordsConfig {
             format_style: None,
             format_config: Some(format_config_user),
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_config = word_config.resolve_formatter_config().unwrap();
@@ -351,6 +616,9 @@ mod test {
         let mut word_config = WordsConfig {
             format_style: None,
             format_config: None,
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_config = word_config.resolve_formatter_config();
@@ -375,6 +643,8 @@ mod test {
             example: Some("test".to_string()),
             synonyms_title: Some("test".to_string()),
             synonyms: Some("test".to_string()),
+            antonyms_title: None,
+            antonyms: None,
             reset: Some("test".to_string()),
         };
 
@@ -389,6 +659,9 @@ mod test {
         let mut word_config = WordsConfig {
             format_style: Some(style_config),
             format_config: Some(format_config_user),
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_config = word_config.resolve_config();
@@ -456,12 +729,17 @@ mod test {
             example: Some("test".to_string()),
             synonyms_title: Some("test".to_string()),
             synonyms: Some("test".to_string()),
+            antonyms_title: None,
+            antonyms: None,
             reset: Some("test".to_string()),
         };
 
         let mut word_config = WordsConfig {
             format_style: Some(style_config),
             format_config: None,
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_config = word_config.resolve_config();
@@ -508,6 +786,9 @@ mod test {
         let mut word_config = WordsConfig {
             format_style: None,
             format_config: Some(format_config_user),
+            dictionary: None,
+            api: None,
+            cache: None,
         };
 
         let formatter_config = word_config.resolve_config();
@@ -531,6 +812,156 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_resolve_provider_config_with_user_config() {
+        let mut word_config = WordsConfig {
+            format_style: None,
+            format_config: None,
+            dictionary: Some(DictionaryConfig {
+                provider: Some("test".to_string()),
+                wordnet_path: Some("test".to_string()),
+                stardict_path: Some("test".to_string()),
+                dictd_host: Some("test".to_string()),
+                dictd_port: Some(1),
+                database: Some("test".to_string()),
+                strategy: Some("test".to_string()),
+                retries: Some(1),
+            }),
+            api: None,
+            cache: None,
+        };
+
+        let provider_config = word_config.resolve_provider_config();
+
+        assert_eq!(
+            &provider_config.provider, "test",
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            provider_config.wordnet_path,
+            Some(PathBuf::from("test")),
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            provider_config.stardict_path,
+            Some(PathBuf::from("test")),
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            &provider_config.dictd_host, "test",
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            provider_config.dictd_port, 1,
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            &provider_config.database, "test",
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            &provider_config.strategy, "test",
+            "did not set config correctly"
+        );
+
+        assert_eq!(provider_config.retries, 1, "did not set config correctly");
+    }
+
+    #[test]
+    fn test_resolve_provider_config_with_api() {
+        let mut word_config = WordsConfig {
+            format_style: None,
+            format_config: None,
+            dictionary: None,
+            api: Some(ApiConfig {
+                base_url: Some("http://localhost/entries".to_string()),
+                lang: Some("fr".to_string()),
+                timeout: Some(1),
+                user_agent: Some("test".to_string()),
+                proxy: Some("http://localhost:8080".to_string()),
+            }),
+            cache: None,
+        };
+
+        let request_opts = word_config.resolve_provider_config().request_opts;
+
+        assert_eq!(
+            &request_opts.url, "http://localhost/entries",
+            "did not set config correctly"
+        );
+
+        assert_eq!(&request_opts.lang, "fr", "did not set config correctly");
+
+        assert_eq!(
+            request_opts.timeout,
+            Duration::from_secs(1),
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            &request_opts.user_agent, "test",
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            request_opts.proxy.as_deref(),
+            Some("http://localhost:8080"),
+            "did not set config correctly"
+        );
+    }
+
+    #[test]
+    fn test_resolve_provider_config_without_user_config() {
+        let mut word_config = WordsConfig {
+            format_style: None,
+            format_config: None,
+            dictionary: None,
+            api: None,
+            cache: None,
+        };
+
+        let provider_config = word_config.resolve_provider_config();
+
+        assert_eq!(
+            &provider_config.provider, "dictionaryapi",
+            "did not use the default provider"
+        );
+    }
+
+    #[test]
+    fn test_resolve_cache_options() {
+        let mut word_config = WordsConfig {
+            format_style: None,
+            format_config: None,
+            dictionary: None,
+            api: None,
+            cache: Some(CacheConfig {
+                ttl: Some(2),
+                not_found_ttl: Some(3),
+            }),
+        };
+
+        let cache_options = word_config.resolve_cache_options();
+
+        assert_eq!(
+            cache_options.ttl,
+            Duration::from_secs(2 * 24 * 60 * 60),
+            "did not set config correctly"
+        );
+
+        assert_eq!(
+            cache_options.not_found_ttl,
+            Duration::from_secs(3 * 60 * 60),
+            "did not set config correctly"
+        );
+    }
+
     #[test]
     fn test_get_user_config() {
         use std::io::Write;
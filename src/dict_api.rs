@@ -1,12 +1,31 @@
-use std::error::Error;
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use reqwest::{
+    blocking::{Client, Response},
+    header::RETRY_AFTER,
+    Proxy, StatusCode,
+};
 use serde::{Deserialize, Serialize};
-use serde_json::{self, Value};
+use serde_json::Value;
+
+use crate::{
+    error::WordsError, provider::DictionaryProvider, utils::days_from_civil,
+};
 
 /// how to make the request
+///
+/// `url` is the entries endpoint that the language and word get added to so
+/// it can point at a mirror of dictionaryapi
+#[derive(Clone, Debug)]
 pub struct RequestOptions {
-    url: String,
-    lang: String,
+    pub url: String,
+    pub lang: String,
+    pub timeout: Duration,
+    pub user_agent: String,
+    pub proxy: Option<String>,
 }
 
 impl Default for RequestOptions {
@@ -14,6 +33,76 @@ impl Default for RequestOptions {
         Self {
             url: "https://api.dictionaryapi.dev/api/v2/entries".to_string(),
             lang: "en".to_string(),
+            timeout: Duration::from_secs(10),
+            user_agent: format!("words_cli/{}", env!("CARGO_PKG_VERSION")),
+            proxy: None,
+        }
+    }
+}
+
+impl RequestOptions {
+    /// make a http client with the timeout, user agent and proxy
+    fn client(&self) -> Result<Client, WordsError> {
+        let mut builder = Client::builder()
+            .timeout(self.timeout)
+            .user_agent(self.user_agent.as_str());
+
+        if let Some(proxy) = self.proxy.as_ref() {
+            let proxy = Proxy::all(proxy.as_str()).map_err(|e| {
+                WordsError::Config(format!("bad proxy {}: {}", proxy, e))
+            })?;
+
+            builder = builder.proxy(proxy);
+        }
+
+        builder.build().map_err(|e| {
+            WordsError::Config(format!("cant make a http client: {}", e))
+        })
+    }
+}
+
+/// what to do when dictionaryapi is rate limiting us or having problems
+///
+/// the wait doubles after each try starting at `base_delay` but is never
+/// less then what the server asks for with `Retry-After`, if the server asks
+/// for more then `max_delay` we give up right away
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// how long to wait before the next try or nothing if we should give up
+    fn delay(
+        &self,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let backoff = self
+            .base_delay
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) => Some(backoff.max(retry_after)),
+            None => Some(backoff),
         }
     }
 }
@@ -30,6 +119,9 @@ pub struct Definition {
     pub definition: String,
     pub example: Option<String>,
     pub synonyms: Option<Vec<String>>,
+    // not every source has antonyms so leave them out of the json if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub antonyms: Option<Vec<String>>,
 }
 
 // we are serializing from json so we need to use snake case
@@ -45,35 +137,454 @@ pub struct WordData {
     pub word: String,
     pub phonetics: Vec<Phonetic>,
     pub meanings: Vec<Meaning>,
+    // only some sources have this so leave it out of the json if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etymology: Option<String>,
+}
+
+/// get definitions from https://dictionaryapi.dev
+pub struct DictionaryApi {
+    request_opts: RequestOptions,
+    retry_policy: RetryPolicy,
+}
+
+impl DictionaryApi {
+    pub fn new(
+        request_opts: RequestOptions,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            request_opts,
+            retry_policy,
+        }
+    }
+}
+
+impl DictionaryProvider for DictionaryApi {
+    fn get_definition(&self, query: &str) -> Result<Vec<WordData>, WordsError> {
+        get_definition(&self.request_opts, &self.retry_policy, query)
+    }
 }
 
 /// get a definition from dictionaryapi
+///
+/// words like "bass" or "lead" come back as more then one entry so this
+/// returns all of them, 429 and 5xx responses are retried with the policy
 pub fn get_definition(
-    request_opts: RequestOptions,
+    request_opts: &RequestOptions,
+    retry_policy: &RetryPolicy,
     query: &str,
-) -> Result<WordData, Box<dyn Error>> {
-    let url = format!("{}/{}/{}", request_opts.url, request_opts.lang, query);
+) -> Result<Vec<WordData>, WordsError> {
+    let url = format!(
+        "{}/{}/{}",
+        request_opts.url.trim_end_matches('/'),
+        request_opts.lang,
+        query
+    );
+
+    let client = request_opts.client()?;
+
+    let mut attempt = 0;
+
+    let resp = loop {
+        let resp = client.get(&url).send()?;
+
+        let status = resp.status();
 
-    let resp = reqwest::blocking::get(&url)
-        .map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
+        if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error()
+        {
+            break resp;
+        }
+
+        let retry_after = get_retry_after(&resp);
+
+        match retry_policy.delay(attempt, retry_after) {
+            Some(delay) => thread::sleep(delay),
+            None => return Err(give_up_error(status, retry_after, attempt)),
+        }
+
+        attempt += 1;
+    };
 
-    if resp.status() == reqwest::StatusCode::NOT_FOUND {
-        return Err(Box::from("No Definitions Found"));
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Err(WordsError::NotFound("No Definitions Found".to_string()));
     }
 
-    let resp_array = serde_json::from_str(&resp.text()?)?;
+    if !resp.status().is_success() {
+        return Err(WordsError::Network(format!(
+            "dictionaryapi responded with {}",
+            resp.status()
+        )));
+    }
 
-    let word_data: WordData = match resp_array {
-        Value::Array(json_resp) => serde_json::from_value::<WordData>(
-            json_resp
-                .into_iter()
-                .nth(0)
-                .expect("no definitions in api response"),
-        )
-        .expect("cant parse api response in to WordData"),
+    parse_response(&resp.text()?)
+}
+
+/// the error for when we run out of retries
+fn give_up_error(
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    attempts: u32,
+) -> WordsError {
+    if status != StatusCode::TOO_MANY_REQUESTS {
+        return WordsError::Network(format!(
+            "dictionaryapi responded with {} after {} retries",
+            status, attempts
+        ));
+    }
+
+    match retry_after {
+        Some(retry_after) => WordsError::RateLimited(format!(
+            "too many requests to dictionaryapi, try again in {} seconds",
+            // round up so we never say zero when there is still time left
+            retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+        )),
+        None => WordsError::RateLimited(
+            "too many requests to dictionaryapi, try again later".to_string(),
+        ),
+    }
+}
+
+/// read the Retry-After header, it can either be seconds or a http date
+fn get_retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+
+    parse_retry_after(value, SystemTime::now())
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    // a date in the past means we can try now
+    let date = parse_http_date(value)?;
+
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// parse the only date format servers should send, `Sun, 06 Nov 1994
+/// 08:49:37 GMT`
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+        "Nov", "Dec",
+    ];
+
+    let parts: Vec<&str> = date.split_whitespace().collect();
+
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None;
+    }
+
+    let day: u64 = parts[1].parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == parts[2])? as u64 + 1;
+    let year: u64 = parts[3].parse().ok()?;
+
+    let time: Vec<u64> = parts[4]
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    if time.len() != 3 || year < 1970 || day == 0 || day > 31 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day)?;
+
+    let seconds = days * 86_400 + time[0] * 3600 + time[1] * 60 + time[2];
+
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// turn the json array from dictionaryapi in to a list of entries
+fn parse_response(resp_text: &str) -> Result<Vec<WordData>, WordsError> {
+    let resp_array = serde_json::from_str(resp_text)?;
+
+    let entries: Vec<WordData> = match resp_array {
+        Value::Array(json_resp) => json_resp
+            .into_iter()
+            .map(serde_json::from_value::<WordData>)
+            .collect::<Result<_, _>>()?,
+
+        _ => {
+            return Err(WordsError::Parse(
+                "api response structure has changed or is malformed"
+                    .to_string(),
+            ))
+        }
+    };
+
+    if entries.is_empty() {
+        return Err(WordsError::NotFound("No Definitions Found".to_string()));
+    }
+
+    Ok(entries)
+}
 
-        _ => panic!("api response structure has changed or is malformed"),
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread::JoinHandle,
     };
 
-    Ok(word_data)
+    const ENTRY: &str = r#"[{"word": "test", "phonetics": [], "meanings": [{"partOfSpeech": "noun", "definitions": [{"definition": "A test."}]}]}]"#;
+
+    // answer one request per connection with the canned responses in order
+    // and return the request line and headers of every request
+    fn mock_server(
+        responses: Vec<String>,
+    ) -> (String, JoinHandle<Vec<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+
+                let mut reader = BufReader::new(stream);
+
+                let mut request = Vec::new();
+
+                let mut line = String::new();
+
+                // read the request up to the blank line after the headers
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n"
+                {
+                    request.push(line.trim_end().to_string());
+
+                    line.clear();
+                }
+
+                requests.push(request);
+
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+
+            requests
+        });
+
+        (url, handle)
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+            status,
+            body.len(),
+            headers,
+            body
+        )
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn request_opts(url: String) -> RequestOptions {
+        RequestOptions {
+            url,
+            ..RequestOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_get_definition_request_options() {
+        let (url, server) = mock_server(vec![response("200 OK", "", ENTRY)]);
+
+        let request_opts = RequestOptions {
+            url: format!("{}/mirror/", url),
+            lang: "fr".to_string(),
+            user_agent: "test agent".to_string(),
+            ..RequestOptions::default()
+        };
+
+        get_definition(&request_opts, &fast_policy(0), "test").unwrap();
+
+        let requests = server.join().unwrap();
+
+        assert_eq!(requests[0][0], "GET /mirror/fr/test HTTP/1.1");
+        assert!(
+            requests[0]
+                .iter()
+                .any(|header| header.to_lowercase() == "user-agent: test agent"),
+            "did not send the user agent"
+        );
+    }
+
+    #[test]
+    fn test_request_options_bad_proxy() {
+        let request_opts = RequestOptions {
+            proxy: Some("not a url".to_string()),
+            ..RequestOptions::default()
+        };
+
+        match request_opts.client() {
+            Err(WordsError::Config(_)) => {}
+            Err(err) => panic!("got the wrong error: {:?}", err),
+            Ok(_) => panic!("made a client with a bad proxy"),
+        }
+    }
+
+    #[test]
+    fn test_get_definition_retries_server_errors() {
+        let (url, server) = mock_server(vec![
+            response("503 Service Unavailable", "", ""),
+            response("429 Too Many Requests", "Retry-After: 0\r\n", ""),
+            response("200 OK", "", ENTRY),
+        ]);
+
+        let entries =
+            get_definition(&request_opts(url), &fast_policy(3), "test")
+                .unwrap();
+
+        assert_eq!(entries[0].word, "test");
+        assert_eq!(server.join().unwrap().len(), 3, "did not retry");
+    }
+
+    #[test]
+    fn test_get_definition_runs_out_of_retries() {
+        let (url, server) = mock_server(vec![
+            response(
+                "429 Too Many Requests",
+                "Retry-After: 0\r\n",
+                ""
+            );
+            3
+        ]);
+
+        match get_definition(&request_opts(url), &fast_policy(2), "test") {
+            Err(WordsError::RateLimited(_)) => {}
+            Err(err) => panic!("got the wrong error: {:?}", err),
+            Ok(_) => panic!("got a definition from a rate limit"),
+        }
+
+        assert_eq!(server.join().unwrap().len(), 3, "did not retry twice");
+    }
+
+    #[test]
+    fn test_get_definition_long_retry_after() {
+        let (url, server) = mock_server(vec![response(
+            "429 Too Many Requests",
+            "Retry-After: 120\r\n",
+            "",
+        )]);
+
+        match get_definition(&request_opts(url), &fast_policy(3), "test") {
+            Err(WordsError::RateLimited(msg)) => assert_eq!(
+                msg,
+                "too many requests to dictionaryapi, try again in 120 seconds"
+            ),
+            Err(err) => panic!("got the wrong error: {:?}", err),
+            Ok(_) => panic!("got a definition from a rate limit"),
+        }
+
+        assert_eq!(server.join().unwrap().len(), 1, "waited for too long");
+    }
+
+    #[test]
+    fn test_get_definition_server_error() {
+        let (url, server) =
+            mock_server(vec![response("500 Internal Server Error", "", ""); 2]);
+
+        match get_definition(&request_opts(url), &fast_policy(1), "test") {
+            Err(WordsError::Network(_)) => {}
+            Err(err) => panic!("got the wrong error: {:?}", err),
+            Ok(_) => panic!("got a definition from a server error"),
+        }
+
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_717);
+
+        assert_eq!(parse_retry_after("60", now), Some(Duration::from_secs(60)));
+
+        // Sun, 06 Nov 1994 08:49:37 GMT is 784111777
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::from_secs(60))
+        );
+
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now),
+            Some(Duration::from_secs(0)),
+            "a date in the past should not wait"
+        );
+
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+        };
+
+        assert_eq!(policy.delay(0, None), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(2, None), Some(Duration::from_secs(4)));
+        assert_eq!(policy.delay(4, None), Some(Duration::from_secs(10)));
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3)),
+            "did not honor Retry-After"
+        );
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(11))), None);
+        assert_eq!(policy.delay(5, None), None, "did not stop retrying");
+    }
+
+    #[test]
+    fn test_parse_response_keeps_every_entry() {
+        let resp_text = r#"[
+            {"word": "bass", "phonetics": [], "meanings": [{"partOfSpeech": "noun", "definitions": [{"definition": "A fish."}]}]},
+            {"word": "bass", "phonetics": [], "meanings": [{"partOfSpeech": "noun", "definitions": [{"definition": "A low sound."}]}]}
+        ]"#;
+
+        let entries = parse_response(resp_text).unwrap();
+
+        let definitions: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.meanings[0].definitions[0].definition.as_str())
+            .collect();
+
+        assert_eq!(
+            definitions,
+            vec!["A fish.", "A low sound."],
+            "did not keep every entry"
+        );
+    }
+
+    #[test]
+    fn test_parse_response_not_an_array() {
+        match parse_response(r#"{"title": "something else"}"#) {
+            Ok(_) => panic!("got entries from a malformed response"),
+            Err(WordsError::Parse(_)) => {}
+            Err(err) => panic!("got the wrong error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_response_empty() {
+        match parse_response("[]") {
+            Ok(_) => panic!("got entries from an empty response"),
+            Err(err) => assert_eq!(err.to_string(), "No Definitions Found"),
+        }
+    }
 }
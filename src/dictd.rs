@@ -0,0 +1,751 @@
+//! a client for the DICT protocol, RFC 2229
+//!
+//! this is what `dictd` and the other dict servers speak, every command gets a
+//! status line back and some are followed by text that ends with a line that
+//! only has a `.`
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use crate::{
+    dict_api::{Definition, Meaning, WordData},
+    error::WordsError,
+    provider::DictionaryProvider,
+};
+
+/// a definition from a DEFINE command
+pub struct DictDefinition {
+    pub word: String,
+    pub database: String,
+    pub description: String,
+    pub text: String,
+}
+
+/// a connection to a dict server
+pub struct DictClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl DictClient {
+    /// connect and read the servers banner
+    pub fn connect(host: &str, port: u16) -> Result<Self, WordsError> {
+        let stream = TcpStream::connect((host, port)).map_err(|e| {
+            WordsError::Network(format!(
+                "cant connect to dict server {}:{}: {}",
+                host, port, e
+            ))
+        })?;
+
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .map_err(network_error)?;
+
+        let writer = stream.try_clone().map_err(network_error)?;
+
+        let mut client = Self {
+            reader: BufReader::new(stream),
+            writer,
+        };
+
+        let (code, line) = client.read_status()?;
+
+        if code != 220 {
+            return Err(response_error(code, &line));
+        }
+
+        // let the server know who we are, this is optional so ignore the status
+        client.command("CLIENT words_cli")?;
+
+        Ok(client)
+    }
+
+    /// DEFINE a word in the given database, `*` for all and `!` for the first
+    /// database with a match
+    pub fn define(
+        &mut self,
+        database: &str,
+        word: &str,
+    ) -> Result<Vec<DictDefinition>, WordsError> {
+        let (code, line) =
+            self.command(&format!("DEFINE {} {}", database, quote(word)))?;
+
+        match code {
+            150 => {}
+            552 => return Ok(Vec::new()),
+            _ => return Err(response_error(code, &line)),
+        }
+
+        let mut definitions = Vec::new();
+
+        loop {
+            let (code, line) = self.read_status()?;
+
+            match code {
+                151 => {
+                    let mut params = parse_params(&line).into_iter().skip(1);
+
+                    definitions.push(DictDefinition {
+                        word: params.next().unwrap_or_default(),
+                        database: params.next().unwrap_or_default(),
+                        description: params.next().unwrap_or_default(),
+                        text: self.read_text()?.join("\n"),
+                    });
+                }
+                250 => break,
+                _ => return Err(response_error(code, &line)),
+            }
+        }
+
+        Ok(definitions)
+    }
+
+    /// MATCH words in a database with a strategy, this returns a list of
+    /// databases and words
+    pub fn match_words(
+        &mut self,
+        database: &str,
+        strategy: &str,
+        word: &str,
+    ) -> Result<Vec<(String, String)>, WordsError> {
+        let (code, line) = self.command(&format!(
+            "MATCH {} {} {}",
+            database,
+            strategy,
+            quote(word)
+        ))?;
+
+        match code {
+            152 => self.read_pairs(),
+            552 => Ok(Vec::new()),
+            _ => Err(response_error(code, &line)),
+        }
+    }
+
+    /// SHOW DB to get the databases and there descriptions
+    pub fn show_databases(
+        &mut self,
+    ) -> Result<Vec<(String, String)>, WordsError> {
+        let (code, line) = self.command("SHOW DB")?;
+
+        match code {
+            110 => self.read_pairs(),
+            554 => Ok(Vec::new()),
+            _ => Err(response_error(code, &line)),
+        }
+    }
+
+    /// SHOW STRAT to get the match strategies and there descriptions
+    pub fn show_strategies(
+        &mut self,
+    ) -> Result<Vec<(String, String)>, WordsError> {
+        let (code, line) = self.command("SHOW STRAT")?;
+
+        match code {
+            111 => self.read_pairs(),
+            555 => Ok(Vec::new()),
+            _ => Err(response_error(code, &line)),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<(u16, String), WordsError> {
+        self.writer
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .map_err(network_error)?;
+
+        self.read_status()
+    }
+
+    fn read_line(&mut self) -> Result<String, WordsError> {
+        let mut line = String::new();
+
+        if self.reader.read_line(&mut line).map_err(network_error)? == 0 {
+            return Err(WordsError::Network(
+                "dict server closed the connection".to_string(),
+            ));
+        }
+
+        Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+    }
+
+    fn read_status(&mut self) -> Result<(u16, String), WordsError> {
+        let line = self.read_line()?;
+
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| {
+                WordsError::Parse(format!(
+                    "bad status line from dict server: {}",
+                    line
+                ))
+            })?;
+
+        Ok((code, line))
+    }
+
+    /// read a text response up to the lone `.` and the status after it
+    fn read_text(&mut self) -> Result<Vec<String>, WordsError> {
+        let mut lines = Vec::new();
+
+        loop {
+            let line = self.read_line()?;
+
+            if line == "." {
+                break;
+            }
+
+            // lines starting with a `.` get an extra `.` added by the server
+            if line.starts_with("..") {
+                lines.push(line[1..].to_string());
+            } else {
+                lines.push(line);
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// read a text response of `name "description"` lines
+    fn read_pairs(&mut self) -> Result<Vec<(String, String)>, WordsError> {
+        let pairs = self
+            .read_text()?
+            .iter()
+            .map(|line| {
+                let mut params = parse_params(line).into_iter();
+
+                (
+                    params.next().unwrap_or_default(),
+                    params.next().unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        let (code, line) = self.read_status()?;
+
+        if code != 250 {
+            return Err(response_error(code, &line));
+        }
+
+        Ok(pairs)
+    }
+}
+
+impl Drop for DictClient {
+    fn drop(&mut self) {
+        // the connection is going away anyway so just try and be polite
+        let _ = self.writer.write_all(b"QUIT\r\n");
+    }
+}
+
+/// get definitions from a dict server
+pub struct Dictd {
+    host: String,
+    port: u16,
+    database: String,
+    strategy: String,
+}
+
+impl Dictd {
+    pub fn new(host: &str, port: u16, database: &str, strategy: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            database: database.to_string(),
+            strategy: strategy.to_string(),
+        }
+    }
+}
+
+impl DictionaryProvider for Dictd {
+    fn get_definition(&self, query: &str) -> Result<Vec<WordData>, WordsError> {
+        let mut client = DictClient::connect(&self.host, self.port)?;
+
+        let definitions = client.define(&self.database, query)?;
+
+        if definitions.is_empty() {
+            let mut matches: Vec<String> = client
+                .match_words(&self.database, &self.strategy, query)?
+                .into_iter()
+                .map(|(_, word)| word)
+                .collect();
+
+            // the same word can come from more then one database
+            let mut seen = HashSet::new();
+
+            matches.retain(|word| seen.insert(word.to_lowercase()));
+
+            if matches.is_empty() {
+                return Err(WordsError::NotFound(
+                    "No Definitions Found".to_string(),
+                ));
+            }
+
+            return Err(WordsError::NotFound(format!(
+                "No Definitions Found, did you mean: {}",
+                matches.join(", ")
+            )));
+        }
+
+        let word = definitions[0].word.clone();
+
+        let meanings = definitions
+            .into_iter()
+            .flat_map(|definition| {
+                let source = if definition.description.is_empty() {
+                    definition.database
+                } else {
+                    definition.description
+                };
+
+                text_to_meanings(&definition.word, &source, &definition.text)
+            })
+            .collect();
+
+        Ok(vec![WordData {
+            word,
+            phonetics: Vec::new(),
+            meanings,
+            etymology: None,
+        }])
+    }
+
+    fn databases(&self) -> Result<Vec<(String, String)>, WordsError> {
+        DictClient::connect(&self.host, self.port)?.show_databases()
+    }
+
+    fn strategies(&self) -> Result<Vec<(String, String)>, WordsError> {
+        DictClient::connect(&self.host, self.port)?.show_strategies()
+    }
+}
+
+// anything that goes wrong with the socket is a network problem
+fn network_error(err: io::Error) -> WordsError {
+    WordsError::Network(err.to_string())
+}
+
+/// make an error from an unexpected status line
+///
+/// only 420 and 421 mean the server cant be used right now, a bad database or
+/// strategy is a mistake in what was given and the other 5xx codes are
+/// commands or parameters the server wont take
+fn response_error(code: u16, line: &str) -> WordsError {
+    let message = format!(
+        "dict server error {}: {}",
+        code,
+        line.get(4..).unwrap_or("")
+    );
+
+    match code {
+        420 | 421 => WordsError::Network(message),
+        550 | 551 => WordsError::BadInput(message),
+        500..=599 => WordsError::Config(message),
+        _ => WordsError::Parse(message),
+    }
+}
+
+/// quote a word for a command if it needs it
+fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.contains(&[' ', '"', '\'', '\\'][..]) {
+        return word.to_string();
+    }
+
+    format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// split a line in to words and quoted strings
+fn parse_params(line: &str) -> Vec<String> {
+    let mut params = Vec::new();
+
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut param = String::new();
+
+        if c == '"' {
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => param.extend(chars.next()),
+                    _ => param.push(c),
+                }
+            }
+        } else {
+            param.push(c);
+
+            while let Some(c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+
+                param.push(*c);
+                chars.next();
+            }
+        }
+
+        params.push(param);
+    }
+
+    params
+}
+
+/// the part of speech for a WordNet style tag like the `adj` in `adj 1:`
+fn part_of_speech(tag: &str) -> Option<&'static str> {
+    match tag {
+        "n" => Some("noun"),
+        "v" => Some("verb"),
+        "adj" => Some("adjective"),
+        "adv" => Some("adverb"),
+        _ => None,
+    }
+}
+
+/// take the part of speech and sense number off the front of a paragraph,
+/// `adj 1: bad` gives `(Some("adjective"), "bad")` and `2: bad` gives
+/// `(None, "bad")`
+fn split_sense(paragraph: &str) -> (Option<&'static str>, &str) {
+    let mut words = paragraph.splitn(3, ' ');
+
+    let first = words.next().unwrap_or("");
+
+    let is_sense = |word: &str| {
+        matches!(word.strip_suffix(':').map(str::parse::<u32>), Some(Ok(_)))
+    };
+
+    if is_sense(first) {
+        return (None, paragraph[first.len()..].trim_start());
+    }
+
+    if let (Some(pos), Some(sense)) = (part_of_speech(first), words.next()) {
+        if is_sense(sense) {
+            let rest = words.next().unwrap_or("");
+
+            return (Some(pos), rest);
+        }
+    }
+
+    (None, paragraph)
+}
+
+/// split a plain text definition in to meanings and paragraphs
+///
+/// the text starts with the headword on its own line so that is dropped, a
+/// WordNet style `adj 1:` starts a new meaning with that part of speech and
+/// anything without one goes under `source`
+fn text_to_meanings(word: &str, source: &str, text: &str) -> Vec<Meaning> {
+    let mut lines = text.lines().peekable();
+
+    if let Some(first) = lines.peek() {
+        let first = first.trim().to_lowercase();
+
+        if first.starts_with(&word.to_lowercase()) {
+            lines.next();
+        }
+    }
+
+    let text = lines.collect::<Vec<&str>>().join("\n");
+
+    let mut meanings: Vec<Meaning> = Vec::new();
+
+    let paragraphs = text
+        .split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .filter(|paragraph| !paragraph.is_empty());
+
+    for paragraph in paragraphs {
+        let (pos, definition) = split_sense(&paragraph);
+
+        let definition = Definition {
+            definition: definition.to_string(),
+            example: None,
+            synonyms: None,
+            antonyms: None,
+        };
+
+        match (pos, meanings.last_mut()) {
+            (None, Some(meaning)) => meaning.definitions.push(definition),
+            _ => meanings.push(Meaning {
+                partOfSpeech: pos.unwrap_or(source).to_string(),
+                definitions: vec![definition],
+            }),
+        }
+    }
+
+    meanings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{net::TcpListener, thread};
+
+    // a dict server that knows one word and answers the commands we send
+    fn fake_dictd() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut writer = stream.unwrap();
+                let mut reader = BufReader::new(writer.try_clone().unwrap());
+
+                writer
+                    .write_all(b"220 fake dictd <auth.mime> <1@fake>\r\n")
+                    .unwrap();
+
+                let mut line = String::new();
+
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    let response = match line.trim_end() {
+                        "CLIENT words_cli" => "250 ok\r\n".to_string(),
+                        "DEFINE * flagrant" => "150 1 definitions retrieved\r\n\
+                             151 \"flagrant\" wn \"WordNet (r) 3.0 (2006)\"\r\n\
+                             flagrant\r\n    adj 1: conspicuously and\r\n\
+                             \x20          outrageously bad\r\n\r\n\
+                             ..dotted line\r\n\r\n\
+                             \x20   2: glaring\r\n\r\n\
+                             \x20   n 1: a word\r\n\
+                             .\r\n\
+                             250 ok\r\n"
+                            .to_string(),
+                        "DEFINE * flgrent" | "DEFINE * zzzz" => {
+                            "552 no match\r\n".to_string()
+                        }
+                        "MATCH * lev flgrent" => "152 4 matches found\r\n\
+                             wn \"flagrant\"\r\n\
+                             wn \"fragrant\"\r\n\
+                             gcide \"Flagrant\"\r\n\
+                             gcide \"fragrant\"\r\n\
+                             .\r\n\
+                             250 ok\r\n"
+                            .to_string(),
+                        "MATCH * lev zzzz" => "552 no match\r\n".to_string(),
+                        "DEFINE nope flagrant" => {
+                            "550 invalid database\r\n".to_string()
+                        }
+                        "MATCH * nope flgrent" => {
+                            "551 invalid strategy\r\n".to_string()
+                        }
+                        "SHOW DB" => "110 2 databases present\r\n\
+                             wn \"WordNet (r) 3.0 (2006)\"\r\n\
+                             gcide \"The Collaborative International Dictionary of English\"\r\n\
+                             .\r\n\
+                             250 ok\r\n"
+                            .to_string(),
+                        "SHOW STRAT" => "111 2 strategies present\r\n\
+                             exact \"Match headwords exactly\"\r\n\
+                             lev \"Match headwords within Levenshtein distance one\"\r\n\
+                             .\r\n\
+                             250 ok\r\n"
+                            .to_string(),
+                        "QUIT" => {
+                            let _ = writer.write_all(b"221 bye\r\n");
+                            break;
+                        }
+                        _ => "500 unknown command\r\n".to_string(),
+                    };
+
+                    writer.write_all(response.as_bytes()).unwrap();
+
+                    line.clear();
+                }
+            }
+        });
+
+        port
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = parse_params(r#"151 "ice cream" wn "WordNet \"3.0\"""#);
+
+        assert_eq!(
+            params,
+            vec!["151", "ice cream", "wn", "WordNet \"3.0\""],
+            "did not split the line correctly"
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("word"), "word", "quoted a plain word");
+        assert_eq!(quote("ice cream"), "\"ice cream\"", "did not quote");
+        assert_eq!(quote("a\"b"), "\"a\\\"b\"", "did not escape quotes");
+    }
+
+    #[test]
+    fn test_define() {
+        let port = fake_dictd();
+
+        let mut client = DictClient::connect("127.0.0.1", port).unwrap();
+
+        let definitions = client.define("*", "flagrant").unwrap();
+
+        assert_eq!(definitions.len(), 1, "got the wrong amount of definitions");
+
+        let definition = &definitions[0];
+
+        assert_eq!(definition.word, "flagrant");
+        assert_eq!(definition.database, "wn");
+        assert_eq!(definition.description, "WordNet (r) 3.0 (2006)");
+        assert_eq!(
+            definition.text,
+            "flagrant\n    adj 1: conspicuously and\n           outrageously \
+             bad\n\n.dotted line\n\n    2: glaring\n\n    n 1: a word",
+            "did not read the definition text correctly"
+        );
+    }
+
+    #[test]
+    fn test_bad_database_and_strategy() {
+        let port = fake_dictd();
+
+        let mut client = DictClient::connect("127.0.0.1", port).unwrap();
+
+        let err = client.define("nope", "flagrant").err().unwrap();
+
+        assert_eq!(err.kind(), "bad input", "{}", err);
+
+        let err = client.match_words("*", "nope", "flgrent").err().unwrap();
+
+        assert_eq!(err.kind(), "bad input", "{}", err);
+    }
+
+    #[test]
+    fn test_response_error() {
+        let kind = |code| response_error(code, "000 message").kind();
+
+        assert_eq!(kind(420), "network error");
+        assert_eq!(kind(421), "network error");
+        assert_eq!(kind(500), "config error");
+        assert_eq!(kind(530), "config error");
+        assert_eq!(kind(250), "parse error");
+    }
+
+    #[test]
+    fn test_show_databases_and_strategies() {
+        let port = fake_dictd();
+
+        let mut client = DictClient::connect("127.0.0.1", port).unwrap();
+
+        let databases = client.show_databases().unwrap();
+
+        assert_eq!(databases.len(), 2, "got the wrong amount of databases");
+        assert_eq!(databases[0].0, "wn");
+        assert_eq!(
+            databases[1].1,
+            "The Collaborative International Dictionary of English"
+        );
+
+        let strategies = client.show_strategies().unwrap();
+
+        assert_eq!(
+            strategies
+                .iter()
+                .map(|s| s.0.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["exact", "lev"],
+            "got the wrong strategies"
+        );
+    }
+
+    #[test]
+    fn test_get_definition() {
+        let port = fake_dictd();
+
+        let dictd = Dictd::new("127.0.0.1", port, "*", "lev");
+
+        let word_data = dictd.get_definition("flagrant").unwrap().remove(0);
+
+        assert_eq!(word_data.word, "flagrant");
+
+        let meanings: Vec<(&str, Vec<&str>)> = word_data
+            .meanings
+            .iter()
+            .map(|meaning| {
+                (
+                    meaning.partOfSpeech.as_str(),
+                    meaning
+                        .definitions
+                        .iter()
+                        .map(|d| d.definition.as_str())
+                        .collect(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            meanings,
+            vec![
+                (
+                    "adjective",
+                    vec![
+                        "conspicuously and outrageously bad",
+                        ".dotted line",
+                        "glaring"
+                    ]
+                ),
+                ("noun", vec!["a word"]),
+            ],
+            "did not split the text in to meanings and definitions"
+        );
+    }
+
+    #[test]
+    fn test_text_to_meanings_without_parts_of_speech() {
+        let meanings = text_to_meanings(
+            "Flagrant",
+            "gcide",
+            "Flagrant \\Fla\"grant\\, a.\n\n   Flaming into notice.\n\n   \
+             Burning.",
+        );
+
+        assert_eq!(meanings.len(), 1);
+        assert_eq!(meanings[0].partOfSpeech, "gcide");
+
+        assert_eq!(
+            meanings[0]
+                .definitions
+                .iter()
+                .map(|d| d.definition.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Flaming into notice.", "Burning."],
+            "did not drop the headword line"
+        );
+    }
+
+    #[test]
+    fn test_get_definition_not_found() {
+        let port = fake_dictd();
+
+        let dictd = Dictd::new("127.0.0.1", port, "*", "lev");
+
+        match dictd.get_definition("flgrent") {
+            Ok(_) => panic!("found a word that does not exist"),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "No Definitions Found, did you mean: flagrant, fragrant",
+                "did not suggest the matches"
+            ),
+        }
+
+        match dictd.get_definition("zzzz") {
+            Ok(_) => panic!("found a word that does not exist"),
+            Err(err) => assert_eq!(err.to_string(), "No Definitions Found"),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{error::Error, fmt};
+
+/// everything that can go wrong, each kind has its own exit code so scripts
+/// can tell a missing word apart from the network being down
+#[derive(Debug)]
+pub enum WordsError {
+    /// the dictionary could not be reached
+    Network(String),
+    /// the dictionary does not have the word
+    NotFound(String),
+    /// the dictionary wants us to slow down
+    RateLimited(String),
+    /// a response or dictionary file was not what we expected
+    Parse(String),
+    /// reading or writing the cache failed
+    Cache(String),
+    /// the config or a path in it is wrong
+    Config(String),
+    /// aspell could not be started or talked to
+    SpellChecker(String),
+    /// the input given on the cli or stdin cant be used
+    BadInput(String),
+    /// a document has misspelled words in it
+    Misspelled(String),
+}
+
+impl WordsError {
+    /// a short name for the kind of error to put in front of the message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WordsError::Network(_) => "network error",
+            WordsError::NotFound(_) => "not found",
+            WordsError::RateLimited(_) => "rate limited",
+            WordsError::Parse(_) => "parse error",
+            WordsError::Cache(_) => "cache error",
+            WordsError::Config(_) => "config error",
+            WordsError::SpellChecker(_) => "spell checker error",
+            WordsError::BadInput(_) => "bad input",
+            WordsError::Misspelled(_) => "misspelled",
+        }
+    }
+
+    /// the code to exit with, not found is 1 like grep when nothing matches
+    /// and clap already uses 2 for bad arguments
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WordsError::NotFound(_) => 1,
+            WordsError::Network(_) => 3,
+            WordsError::RateLimited(_) => 4,
+            WordsError::Parse(_) => 5,
+            WordsError::Cache(_) => 6,
+            WordsError::Config(_) => 7,
+            WordsError::SpellChecker(_) => 8,
+            WordsError::BadInput(_) => 9,
+            WordsError::Misspelled(_) => 10,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            WordsError::Network(msg)
+            | WordsError::NotFound(msg)
+            | WordsError::RateLimited(msg)
+            | WordsError::Parse(msg)
+            | WordsError::Cache(msg)
+            | WordsError::Config(msg)
+            | WordsError::SpellChecker(msg)
+            | WordsError::BadInput(msg)
+            | WordsError::Misspelled(msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for WordsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl Error for WordsError {}
+
+impl From<serde_json::Error> for WordsError {
+    fn from(err: serde_json::Error) -> Self {
+        WordsError::Parse(err.to_string())
+    }
+}
+
+impl From<toml::de::Error> for WordsError {
+    fn from(err: toml::de::Error) -> Self {
+        WordsError::Config(err.to_string())
+    }
+}
+
+impl From<reqwest::Error> for WordsError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            WordsError::Parse(err.to_string())
+        } else {
+            WordsError::Network(err.to_string())
+        }
+    }
+}
+
+impl From<ispell::Error> for WordsError {
+    fn from(err: ispell::Error) -> Self {
+        WordsError::SpellChecker(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_different() {
+        let errors = [
+            WordsError::Network(String::new()),
+            WordsError::NotFound(String::new()),
+            WordsError::RateLimited(String::new()),
+            WordsError::Parse(String::new()),
+            WordsError::Cache(String::new()),
+            WordsError::Config(String::new()),
+            WordsError::SpellChecker(String::new()),
+            WordsError::BadInput(String::new()),
+            WordsError::Misspelled(String::new()),
+        ];
+
+        let mut codes: Vec<i32> =
+            errors.iter().map(|e| e.exit_code()).collect();
+
+        codes.sort_unstable();
+        codes.dedup();
+
+        assert_eq!(codes.len(), errors.len(), "two errors share an exit code");
+        assert!(!codes.contains(&0), "an error exits with success");
+        assert!(!codes.contains(&2), "an error exits like a clap error");
+    }
+
+    #[test]
+    fn test_display_is_the_message() {
+        let err = WordsError::NotFound("No Definitions Found".to_string());
+
+        assert_eq!(err.to_string(), "No Definitions Found");
+        assert_eq!(err.kind(), "not found");
+    }
+}
@@ -9,6 +9,8 @@ pub struct FormatterStyle {
     pub example: String,
     pub synonyms_title: String,
     pub synonyms: String,
+    pub antonyms_title: String,
+    pub antonyms: String,
     pub reset: String,
 }
 
@@ -22,6 +24,8 @@ impl Default for FormatterStyle {
             example: "\x1b[3m".to_string(),
             synonyms_title: "\x1b[4m".to_string(),
             synonyms: "\x1b[0m".to_string(),
+            antonyms_title: "\x1b[4m".to_string(),
+            antonyms: "\x1b[0m".to_string(),
             reset: "\x1b[0m".to_string(),
         }
     }
@@ -48,6 +52,8 @@ impl FormatterConfig {
         self.format_style.example = String::new();
         self.format_style.synonyms_title = String::new();
         self.format_style.synonyms = String::new();
+        self.format_style.antonyms_title = String::new();
+        self.format_style.antonyms = String::new();
         self.format_style.reset = String::new();
     }
 
@@ -76,6 +82,7 @@ impl Default for FormatterConfig {
     }
 }
 
+#[allow(clippy::assign_op_pattern)]
 fn break_line(
     config: &FormatterConfig,
     line_break: usize,
@@ -168,6 +175,39 @@ macro_rules! format_line {
 fn format_word_data(
     format_conf: &FormatterConfig,
     word_data: &WordData,
+) -> String {
+    format_entry(format_conf, word_data, &word_data.word)
+}
+
+/// format a list of entries, when there is more then one each heading gets a
+/// number so they can be told apart and picked with `--entry`
+fn format_entries(
+    format_conf: &FormatterConfig,
+    entries: &[WordData],
+) -> String {
+    if entries.len() == 1 {
+        return format_word_data(format_conf, &entries[0]);
+    }
+
+    let separator = if format_conf.formatting { "\n\n" } else { " " };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, word_data)| {
+            let heading = format!("{}. {}", i + 1, word_data.word);
+
+            format_entry(format_conf, word_data, &heading)
+        })
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+#[allow(clippy::map_flatten)]
+fn format_entry(
+    format_conf: &FormatterConfig,
+    word_data: &WordData,
+    heading: &str,
 ) -> String {
     // a vec to collect the lines
     let mut output: Vec<String> = Vec::new();
@@ -177,15 +217,17 @@ fn format_word_data(
     let def_spaces = spaces.repeat(2);
     let exa_spaces = spaces.repeat(3);
 
-    // format the queried word
-    let word = format_line!(
-        format_conf,
-        &format_conf.format_style.word,
-        "",
-        &word_data.word,
-    );
+    // format the queried word, an empty heading leaves it out
+    if !heading.is_empty() {
+        let word = format_line!(
+            format_conf,
+            &format_conf.format_style.word,
+            "",
+            heading,
+        );
 
-    output.extend(word);
+        output.extend(word);
+    }
 
     let meanings_len = word_data.meanings.len();
 
@@ -270,6 +312,34 @@ fn format_word_data(
                 }
             }
 
+            // there are only ever a few antonyms so they are all shown even
+            // when the synonyms are not
+            if let Some(ref ants) = definition.antonyms {
+                if format_conf.formatting {
+                    output.push(String::new());
+                }
+
+                let antonyms_title = format!(
+                    "{}{}antonyms{}",
+                    def_spaces,
+                    format_conf.format_style.antonyms_title,
+                    format_conf.format_style.reset,
+                );
+
+                output.push(antonyms_title);
+
+                let formatted_ants = ants.iter().flat_map(|s| {
+                    format_line!(
+                        format_conf,
+                        &format_conf.format_style.antonyms,
+                        &exa_spaces,
+                        s
+                    )
+                });
+
+                output.extend(formatted_ants);
+            }
+
             if format_conf.formatting
                 && definition_len > 1
                 && i + 1 != definition_len
@@ -283,6 +353,30 @@ fn format_word_data(
         }
     }
 
+    if let Some(ref etymology) = word_data.etymology {
+        if format_conf.formatting {
+            output.push(String::new());
+        }
+
+        let etymology_title = format_line!(
+            format_conf,
+            &format_conf.format_style.example_title,
+            &spaces,
+            "etymology",
+        );
+
+        output.extend(etymology_title);
+
+        let etymology_lines = format_line!(
+            format_conf,
+            &format_conf.format_style.definition,
+            &def_spaces,
+            etymology
+        );
+
+        output.extend(etymology_lines);
+    }
+
     if format_conf.formatting {
         output.join("\n")
     } else {
@@ -290,13 +384,29 @@ fn format_word_data(
     }
 }
 
-pub fn print_definition(format_conf: &FormatterConfig, word_data: &WordData) {
-    let word_str = format_word_data(format_conf, word_data);
+/// format the meanings of the entries without the word at the top, like for
+/// the back of a flash card
+pub fn format_meanings(
+    format_conf: &FormatterConfig,
+    entries: &[WordData],
+) -> String {
+    let separator = if format_conf.formatting { "\n\n" } else { " " };
+
+    entries
+        .iter()
+        .map(|word_data| format_entry(format_conf, word_data, ""))
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+pub fn print_definition(format_conf: &FormatterConfig, entries: &[WordData]) {
+    let word_str = format_entries(format_conf, entries);
 
     println!("{}", word_str);
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod test {
     use super::*;
 
@@ -501,6 +611,136 @@ mod test {
         assert_eq!(&word_str, &fake_word_string, "did not format correctly");
     }
 
+    #[test]
+    fn test_format_word_data_antonyms() {
+        let mut fake_word = fake_word_data();
+
+        fake_word.meanings[0].definitions[0].antonyms =
+            Some(vec!["test".to_string()]);
+
+        let mut fake_word_string = make_formatted_text_one();
+
+        fake_word_string.push_str(&make_formatted_text_two());
+        fake_word_string.push_str(
+            "\n\n    \x1b[4mantonyms\x1b[0m\n      \x1b[0mtest\x1b[0m",
+        );
+
+        let fake_conf = FormatterConfig::default();
+
+        let word_str = format_word_data(&fake_conf, &fake_word);
+
+        assert_eq!(&word_str, &fake_word_string, "did not format correctly");
+    }
+
+    #[test]
+    fn test_format_word_data_antonyms_without_synonyms() {
+        let mut fake_word = fake_word_data();
+
+        fake_word.meanings[0].definitions[0].antonyms =
+            Some(vec!["test".to_string()]);
+
+        let mut fake_word_string = make_formatted_text_one();
+
+        fake_word_string.push_str(
+            "\n\n    \x1b[4mantonyms\x1b[0m\n      \x1b[0mtest\x1b[0m",
+        );
+
+        let mut fake_conf = FormatterConfig {
+            synonym_limit: 0,
+            ..Default::default()
+        };
+
+        let word_str = format_word_data(&fake_conf, &fake_word);
+
+        assert_eq!(&word_str, &fake_word_string, "did not show the antonyms");
+
+        fake_conf.format_style.antonyms = "\x1b[31m".to_string();
+
+        let word_str = format_word_data(&fake_conf, &fake_word);
+
+        assert!(
+            word_str.ends_with("\x1b[31mtest\x1b[0m"),
+            "did not use the antonym style: {:?}",
+            word_str
+        );
+    }
+
+    #[test]
+    fn test_format_word_data_etymology() {
+        let mut fake_word = fake_word_data();
+
+        fake_word.etymology = Some("test etymology".to_string());
+
+        let mut fake_word_string = make_formatted_text_one();
+
+        fake_word_string.push_str(
+            "\n\n  \x1b[4metymology\x1b[0m\n    \x1b[0mtest etymology\x1b[0m",
+        );
+
+        let fake_conf = FormatterConfig {
+            synonym_limit: 0,
+            ..Default::default()
+        };
+
+        let word_str = format_word_data(&fake_conf, &fake_word);
+
+        assert_eq!(&word_str, &fake_word_string, "did not format correctly");
+    }
+
+    #[test]
+    fn test_format_entries_one_entry() {
+        let fake_word = fake_word_data();
+
+        let fake_conf = FormatterConfig::default();
+
+        assert_eq!(
+            format_entries(&fake_conf, &[fake_word_data()]),
+            format_word_data(&fake_conf, &fake_word),
+            "numbered a single entry"
+        );
+    }
+
+    #[test]
+    fn test_format_entries_numbered() {
+        let fake_conf = FormatterConfig {
+            synonym_limit: 0,
+            ..Default::default()
+        };
+
+        let entry_text = make_formatted_text_one();
+
+        let fake_word_string = format!(
+            "{}\n\n{}",
+            entry_text.replacen("test", "1. test", 1),
+            entry_text.replacen("test", "2. test", 1)
+        );
+
+        let word_str =
+            format_entries(&fake_conf, &[fake_word_data(), fake_word_data()]);
+
+        assert_eq!(&word_str, &fake_word_string, "did not number the entries");
+    }
+
+    #[test]
+    fn test_format_meanings() {
+        let fake_conf = FormatterConfig {
+            synonym_limit: 0,
+            ..Default::default()
+        };
+
+        let entry_text =
+            make_formatted_text_one().replacen("\x1b[1mtest\x1b[0m\n", "", 1);
+
+        let word_str =
+            format_meanings(&fake_conf, &[fake_word_data(), fake_word_data()]);
+
+        assert_eq!(
+            word_str,
+            format!("{}\n\n{}", entry_text, entry_text),
+            "did not leave out the word"
+        );
+    }
+
     #[test]
     fn test_clear_formating_and_style() {
         let mut formatter_config = FormatterConfig::default();
@@ -0,0 +1,317 @@
+//! the lookup history
+//!
+//! every `-d` and `-s` is saved with when it happened, the query, the word
+//! that was found, where it came from and how it went so you can look back at
+//! what you looked up
+//!
+//! the history is its own SQLite database in the data directory so clearing
+//! the cache does not lose it
+use std::{fmt, fs, path::Path, time::SystemTime};
+
+use rusqlite::{params, Connection, Row};
+
+use crate::{
+    cache::{parse_age, unix_time, BUSY_TIMEOUT},
+    error::WordsError,
+    utils::{get_data_path, parse_date},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS history (
+        id INTEGER PRIMARY KEY,
+        looked_up_at INTEGER NOT NULL,
+        query TEXT NOT NULL,
+        action TEXT NOT NULL,
+        headword TEXT,
+        origin TEXT,
+        result TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS history_looked_up_at
+        ON history (looked_up_at);
+";
+
+/// where a definition came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Cache,
+    Network,
+    /// an old definition used because the dictionary could not be reached
+    StaleCache,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Origin::Cache => write!(f, "cache"),
+            Origin::Network => write!(f, "network"),
+            Origin::StaleCache => write!(f, "stale cache"),
+        }
+    }
+}
+
+/// one `-d` or `-s` call
+///
+/// `headword` is the word that was found, this can be different from the
+/// query like "ran" giving "run", and `origin` is only set for definitions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lookup {
+    pub looked_up_at: i64,
+    pub query: String,
+    pub action: String,
+    pub headword: Option<String>,
+    pub origin: Option<String>,
+    pub result: String,
+}
+
+impl Lookup {
+    pub fn new(action: &str, query: &str) -> Self {
+        Self {
+            looked_up_at: unix_time(SystemTime::now()),
+            query: query.to_owned(),
+            action: action.to_owned(),
+            headword: None,
+            origin: None,
+            result: String::new(),
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            looked_up_at: row.get(0)?,
+            query: row.get(1)?,
+            action: row.get(2)?,
+            headword: row.get(3)?,
+            origin: row.get(4)?,
+            result: row.get(5)?,
+        })
+    }
+}
+
+/// read when a history filter starts, a `YYYY-MM-DD` date is the start of
+/// that day and an age like `2h` is that long ago
+pub fn parse_since(since: &str) -> Result<i64, WordsError> {
+    parse_time(since, false)
+}
+
+/// read when a history filter ends, a `YYYY-MM-DD` date is the end of that
+/// day and an age like `2h` is that long ago
+pub fn parse_until(until: &str) -> Result<i64, WordsError> {
+    parse_time(until, true)
+}
+
+fn parse_time(time: &str, end_of_day: bool) -> Result<i64, WordsError> {
+    if let Some(date) = parse_date(time) {
+        return Ok(if end_of_day {
+            date + 24 * 60 * 60 - 1
+        } else {
+            date
+        });
+    }
+
+    let age = parse_age(time).map_err(|_| {
+        WordsError::BadInput(format!(
+            "{} is not a date like 2020-01-31 or an age like 12h",
+            time
+        ))
+    })?;
+
+    Ok(unix_time(SystemTime::now() - age))
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// open the history in the data directory, making it if it is not there
+    pub fn open() -> Result<Self, WordsError> {
+        let data_path = get_data_path()?;
+
+        if !data_path.exists() {
+            fs::create_dir_all(&data_path)
+                .map_err(|err| WordsError::Cache(err.to_string()))?;
+        }
+
+        Self::open_at(&data_path.join("history.db"))
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self, WordsError> {
+        let conn = Connection::open(path)?;
+
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA journal_mode = WAL", params![], |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn })
+    }
+
+    pub fn add(&self, lookup: &Lookup) -> Result<(), WordsError> {
+        self.conn.execute(
+            "INSERT INTO history
+                (looked_up_at, query, action, headword, origin, result)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                lookup.looked_up_at,
+                lookup.query,
+                lookup.action,
+                lookup.headword,
+                lookup.origin,
+                lookup.result
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// every lookup between since and until, oldest first
+    pub fn list(
+        &self,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<Vec<Lookup>, WordsError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT looked_up_at, query, action, headword, origin, result
+            FROM history
+            WHERE looked_up_at >= ?1 AND looked_up_at <= ?2
+            ORDER BY looked_up_at, id",
+        )?;
+
+        let rows = stmt.query_map(
+            params![since.unwrap_or(i64::MIN), until.unwrap_or(i64::MAX)],
+            Lookup::from_row,
+        )?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// the words definitions were found for, the most recently looked up
+    /// first
+    pub fn found_words(&self) -> Result<Vec<String>, WordsError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT headword FROM history
+            WHERE action = 'define' AND result = 'found'
+                AND headword IS NOT NULL
+            GROUP BY headword
+            ORDER BY MAX(looked_up_at) DESC",
+        )?;
+
+        let rows = stmt.query_map(params![], |row| row.get(0))?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// remove every lookup between since and until, giving back how many
+    /// were removed
+    pub fn clear(
+        &self,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<usize, WordsError> {
+        let removed = self.conn.execute(
+            "DELETE FROM history
+            WHERE looked_up_at >= ?1 AND looked_up_at <= ?2",
+            params![since.unwrap_or(i64::MIN), until.unwrap_or(i64::MAX)],
+        )?;
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::test_utils::TempSetup;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    // a lookup made a while ago
+    fn lookup_at(query: &str, age: Duration) -> Lookup {
+        Lookup {
+            looked_up_at: unix_time(SystemTime::now() - age),
+            ..Lookup::new("define", query)
+        }
+    }
+
+    #[test]
+    fn test_add_and_list() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let history = History::open_at(&root_path.join("history.db")).unwrap();
+
+        let mut lookup = Lookup::new("define", "ran");
+
+        lookup.headword = Some("run".to_string());
+        lookup.origin = Some(Origin::Network.to_string());
+        lookup.result = "found".to_string();
+
+        history.add(&lookup).unwrap();
+        history.add(&Lookup::new("suggest", "flagrnt")).unwrap();
+
+        let lookups = history.list(None, None).unwrap();
+
+        assert_eq!(lookups.len(), 2);
+        assert_eq!(lookups[0], lookup);
+        assert_eq!(lookups[1].query, "flagrnt");
+        assert_eq!(lookups[1].origin, None);
+
+        assert_eq!(history.found_words().unwrap(), vec!["run"]);
+    }
+
+    #[test]
+    fn test_list_and_clear_between() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let history = History::open_at(&root_path.join("history.db")).unwrap();
+
+        history.add(&lookup_at("old", DAY * 10)).unwrap();
+        history.add(&lookup_at("middle", DAY * 3)).unwrap();
+        history
+            .add(&lookup_at("new", Duration::from_secs(0)))
+            .unwrap();
+
+        let queries = |lookups: Vec<Lookup>| {
+            lookups.into_iter().map(|l| l.query).collect::<Vec<_>>()
+        };
+
+        let since = parse_since("5d").unwrap();
+        let until = parse_until("1d").unwrap();
+
+        assert_eq!(
+            queries(history.list(Some(since), None).unwrap()),
+            vec!["middle", "new"]
+        );
+        assert_eq!(
+            queries(history.list(None, Some(until)).unwrap()),
+            vec!["old", "middle"]
+        );
+        assert_eq!(
+            queries(history.list(Some(since), Some(until)).unwrap()),
+            vec!["middle"]
+        );
+
+        assert_eq!(history.clear(None, Some(until)).unwrap(), 2);
+        assert_eq!(queries(history.list(None, None).unwrap()), vec!["new"]);
+    }
+
+    #[test]
+    fn test_parse_since_and_until() {
+        assert_eq!(parse_since("2020-01-31").unwrap(), 1_580_428_800);
+        assert_eq!(parse_until("2020-01-31").unwrap(), 1_580_515_199);
+
+        let now = unix_time(SystemTime::now());
+        let since = parse_since("2h").unwrap();
+
+        assert!((now - 2 * 60 * 60 - since).abs() <= 1);
+
+        assert!(matches!(
+            parse_since("monday"),
+            Err(WordsError::BadInput(_))
+        ));
+    }
+}
@@ -0,0 +1,670 @@
+//! the vocabulary trainer
+//!
+//! the words you looked up, or every cached word, are made in to cards and
+//! quizzed in the terminal, either a definition is shown and you type the
+//! word or the word is shown and you say how well you knew what it means
+//!
+//! reviews are scheduled with SM-2, a word you know comes back after longer
+//! and longer gaps and one you forgot comes back the next day, the cards are
+//! kept in `learn.db` next to the cache
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    iter,
+    path::Path,
+    str::FromStr,
+    time::SystemTime,
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    cache::{cache_key, unix_time, Cache, CacheScope, BUSY_TIMEOUT},
+    dict_api::WordData,
+    error::WordsError,
+    history::History,
+    utils::{format_time, get_data_path},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS cards (
+        word TEXT PRIMARY KEY NOT NULL,
+        repetitions INTEGER NOT NULL,
+        interval INTEGER NOT NULL,
+        ease REAL NOT NULL,
+        due_at INTEGER NOT NULL,
+        reviewed_at INTEGER
+    );
+
+    CREATE INDEX IF NOT EXISTS cards_due_at ON cards (due_at);
+";
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// how easy a new card is, this goes down every time it is hard to remember
+const START_EASE: f64 = 2.5;
+
+/// the lowest ease so a hard word still gets further apart
+const MIN_EASE: f64 = 1.3;
+
+/// the lowest quality that counts as remembering a word
+const REMEMBERED: u8 = 3;
+
+/// a word being learned and when to review it next
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub word: String,
+    /// how many reviews in a row it was remembered
+    pub repetitions: u32,
+    /// days from the last review to the next one
+    pub interval: u32,
+    pub ease: f64,
+    pub due_at: i64,
+}
+
+impl Card {
+    pub fn new(word: &str, now: i64) -> Self {
+        Self {
+            word: word.to_owned(),
+            repetitions: 0,
+            interval: 0,
+            ease: START_EASE,
+            due_at: now,
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            word: row.get(0)?,
+            repetitions: row.get(1)?,
+            interval: row.get(2)?,
+            ease: row.get(3)?,
+            due_at: row.get(4)?,
+        })
+    }
+
+    /// schedule the next review from how well the word was remembered, from
+    /// 0 for not at all to 5 for right away
+    pub fn review(&mut self, quality: u8, now: i64) {
+        let quality = quality.min(5);
+
+        if quality < REMEMBERED {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval) * self.ease).round() as u32,
+            };
+
+            self.repetitions += 1;
+        }
+
+        let missed = f64::from(5 - quality);
+
+        self.ease =
+            (self.ease + 0.1 - missed * (0.08 + missed * 0.02)).max(MIN_EASE);
+
+        self.due_at = now + i64::from(self.interval) * DAY;
+    }
+}
+
+/// where to get new words to learn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordSource {
+    /// words you looked up with `-d`
+    History,
+    /// every cached word
+    Cache,
+}
+
+impl FromStr for WordSource {
+    type Err = WordsError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "history" => Ok(WordSource::History),
+            "cache" => Ok(WordSource::Cache),
+            _ => Err(WordsError::BadInput(format!(
+                "{} is not a word source, use history or cache",
+                source
+            ))),
+        }
+    }
+}
+
+/// how to learn
+///
+/// `limit` is the most words to review at once and `reverse` shows the word
+/// and asks for its definition instead of the other way around
+pub struct LearnOptions {
+    pub source: WordSource,
+    pub limit: usize,
+    pub reverse: bool,
+    /// the provider and language to get the definitions from
+    pub scope: CacheScope,
+}
+
+/// how a review went
+#[derive(Debug, Default, PartialEq)]
+pub struct LearnSummary {
+    pub reviewed: usize,
+    pub remembered: usize,
+}
+
+/// the cards being learned
+pub struct Deck {
+    conn: Connection,
+}
+
+impl Deck {
+    /// open the cards in the data directory, making them if they are not
+    /// there
+    pub fn open() -> Result<Self, WordsError> {
+        let data_path = get_data_path()?;
+
+        if !data_path.exists() {
+            fs::create_dir_all(&data_path)
+                .map_err(|err| WordsError::Cache(err.to_string()))?;
+        }
+
+        Self::open_at(&data_path.join("learn.db"))
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self, WordsError> {
+        let conn = Connection::open(path)?;
+
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.query_row("PRAGMA journal_mode = WAL", params![], |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn })
+    }
+
+    /// the cards due by now, the most overdue first
+    pub fn due(&self, now: i64, limit: usize) -> Result<Vec<Card>, WordsError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT word, repetitions, interval, ease, due_at FROM cards
+            WHERE due_at <= ?1
+            ORDER BY due_at, word
+            LIMIT ?2",
+        )?;
+
+        let rows =
+            stmt.query_map(params![now, limit as i64], Card::from_row)?;
+
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn contains(&self, word: &str) -> Result<bool, WordsError> {
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM cards WHERE word = ?1",
+                params![word],
+                |_| Ok(()),
+            )
+            .optional()?;
+
+        Ok(found.is_some())
+    }
+
+    /// when the next card is due, if there are any
+    pub fn next_due(&self) -> Result<Option<i64>, WordsError> {
+        let due_at = self.conn.query_row(
+            "SELECT MIN(due_at) FROM cards",
+            params![],
+            |row| row.get(0),
+        )?;
+
+        Ok(due_at)
+    }
+
+    pub fn save(&self, card: &Card, now: i64) -> Result<(), WordsError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cards
+                (word, repetitions, interval, ease, due_at, reviewed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                card.word,
+                card.repetitions,
+                card.interval,
+                card.ease,
+                card.due_at,
+                now
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// the cards to review, the ones that are due and then new cards for words
+/// that are not in the deck yet
+pub fn pick_cards(
+    deck: &Deck,
+    words: &[String],
+    limit: usize,
+    now: i64,
+) -> Result<Vec<Card>, WordsError> {
+    let mut cards = deck.due(now, limit)?;
+
+    for word in words {
+        if cards.len() >= limit {
+            break;
+        }
+
+        let key = cache_key(word);
+
+        if !deck.contains(&key)? && !cards.iter().any(|card| card.word == key) {
+            cards.push(Card::new(&key, now));
+        }
+    }
+
+    Ok(cards)
+}
+
+fn io_error(err: io::Error) -> WordsError {
+    WordsError::BadInput(format!("cant talk to the terminal: {}", err))
+}
+
+// hide a word and the words made from it, like "basses" for "bass", so the
+// definition does not give it away
+fn mask_word(text: &str, word: &str) -> String {
+    let word = cache_key(word);
+
+    let hides = |token: &str| {
+        let token = cache_key(token);
+
+        token == word || (word.chars().count() > 3 && token.starts_with(&word))
+    };
+
+    let mut masked = String::with_capacity(text.len());
+    let mut start = None;
+
+    // a space on the end finishes the last token
+    for (index, c) in text.char_indices().chain(iter::once((text.len(), ' '))) {
+        if c.is_alphanumeric() {
+            start.get_or_insert(index);
+
+            continue;
+        }
+
+        if let Some(start) = start.take() {
+            let token = &text[start..index];
+
+            masked.push_str(if hides(token) { "___" } else { token });
+        }
+
+        if index < text.len() {
+            masked.push(c);
+        }
+    }
+
+    masked
+}
+
+// the first definition of each part of speech
+fn write_definitions<W: Write>(
+    output: &mut W,
+    entries: &[WordData],
+    hide: Option<&str>,
+) -> Result<(), WordsError> {
+    for meaning in entries.iter().flat_map(|entry| &entry.meanings) {
+        let definition = match meaning.definitions.first() {
+            Some(definition) => definition,
+            None => continue,
+        };
+
+        let text = match hide {
+            Some(word) => mask_word(&definition.definition, word),
+            None => definition.definition.to_owned(),
+        };
+
+        writeln!(output, "  {}: {}", meaning.partOfSpeech, text)
+            .map_err(io_error)?;
+
+        if let Some(example) = definition.example.as_ref() {
+            let example = match hide {
+                Some(word) => mask_word(example, word),
+                None => example.to_owned(),
+            };
+
+            writeln!(output, "    \"{}\"", example).map_err(io_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+// read a line, nothing means the input is done
+fn read_answer<R: BufRead>(
+    input: &mut R,
+) -> Result<Option<String>, WordsError> {
+    let mut answer = String::new();
+
+    if input.read_line(&mut answer).map_err(io_error)? == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(answer.trim().to_string()))
+    }
+}
+
+// show the definition and ask for the word
+fn ask_word<R: BufRead, W: Write>(
+    entries: &[WordData],
+    input: &mut R,
+    output: &mut W,
+) -> Result<Option<u8>, WordsError> {
+    let headword = &entries[0].word;
+
+    write_definitions(output, entries, Some(headword))?;
+
+    write!(output, "word: ").map_err(io_error)?;
+    output.flush().map_err(io_error)?;
+
+    let answer = match read_answer(input)? {
+        Some(answer) => answer,
+        None => return Ok(None),
+    };
+
+    if cache_key(&answer) == cache_key(headword) {
+        writeln!(output, "right").map_err(io_error)?;
+
+        Ok(Some(4))
+    } else {
+        writeln!(output, "it was {}", headword).map_err(io_error)?;
+
+        Ok(Some(1))
+    }
+}
+
+// show the word and ask how well its definition was remembered
+fn ask_definition<R: BufRead, W: Write>(
+    entries: &[WordData],
+    input: &mut R,
+    output: &mut W,
+) -> Result<Option<u8>, WordsError> {
+    let phonetic = entries[0]
+        .phonetics
+        .first()
+        .map_or("", |phonetic| phonetic.text.as_str());
+
+    writeln!(output, "{} {}", entries[0].word, phonetic).map_err(io_error)?;
+    write!(output, "press enter to see what it means").map_err(io_error)?;
+    output.flush().map_err(io_error)?;
+
+    if read_answer(input)?.is_none() {
+        return Ok(None);
+    }
+
+    write_definitions(output, entries, None)?;
+
+    loop {
+        write!(output, "how well did you know it, 0 to 5: ")
+            .map_err(io_error)?;
+        output.flush().map_err(io_error)?;
+
+        let answer = match read_answer(input)? {
+            Some(answer) => answer,
+            None => return Ok(None),
+        };
+
+        match answer.parse() {
+            Ok(quality) if quality <= 5 => return Ok(Some(quality)),
+            _ => writeln!(output, "give a number from 0 to 5")
+                .map_err(io_error)?,
+        }
+    }
+}
+
+/// quiz the cards and schedule them again, this stops early if the input
+/// ends and skips words that are no longer cached
+pub fn quiz<R: BufRead, W: Write>(
+    cache: &Cache,
+    deck: &Deck,
+    cards: Vec<Card>,
+    reverse: bool,
+    mut input: R,
+    mut output: W,
+) -> Result<LearnSummary, WordsError> {
+    let mut summary = LearnSummary::default();
+
+    let total = cards.len();
+
+    for (index, mut card) in cards.into_iter().enumerate() {
+        let entries = match cache.peek(&card.word)? {
+            Some(cached) => cached.entries,
+            None => continue,
+        };
+
+        writeln!(output, "\n[{}/{}]", index + 1, total).map_err(io_error)?;
+
+        let quality = if reverse {
+            ask_definition(&entries, &mut input, &mut output)?
+        } else {
+            ask_word(&entries, &mut input, &mut output)?
+        };
+
+        let quality = match quality {
+            Some(quality) => quality,
+            None => break,
+        };
+
+        let now = unix_time(SystemTime::now());
+
+        card.review(quality, now);
+        deck.save(&card, now)?;
+
+        summary.reviewed += 1;
+
+        if quality >= REMEMBERED {
+            summary.remembered += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// review the words that are due and some new ones from the source
+pub fn learn<R: BufRead, W: Write>(
+    opts: &LearnOptions,
+    input: R,
+    mut output: W,
+) -> Result<LearnSummary, WordsError> {
+    let cache = Cache::open()?.with_scope(opts.scope.clone());
+    let deck = Deck::open()?;
+
+    let words = match opts.source {
+        WordSource::History => History::open()?.found_words()?,
+        WordSource::Cache => {
+            cache.list("*")?.into_iter().map(|row| row.word).collect()
+        }
+    };
+
+    let now = unix_time(SystemTime::now());
+
+    let cards = pick_cards(&deck, &words, opts.limit, now)?;
+
+    if cards.is_empty() {
+        return match deck.next_due()? {
+            Some(due_at) => {
+                writeln!(
+                    output,
+                    "nothing to review until {}",
+                    format_time(due_at)
+                )
+                .map_err(io_error)?;
+
+                Ok(LearnSummary::default())
+            }
+            None => Err(WordsError::NotFound(
+                "no words to learn, look some up with -d first".to_string(),
+            )),
+        };
+    }
+
+    quiz(&cache, &deck, cards, opts.reverse, input, output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use crate::test_utils::{fake_word_data, TempSetup};
+
+    #[test]
+    fn test_review_schedule() {
+        let mut card = Card::new("test", 0);
+
+        card.review(4, 0);
+        assert_eq!((card.repetitions, card.interval), (1, 1));
+        assert_eq!(card.due_at, DAY);
+
+        card.review(4, DAY);
+        assert_eq!((card.repetitions, card.interval), (2, 6));
+
+        card.review(4, 7 * DAY);
+        assert_eq!((card.repetitions, card.interval), (3, 15));
+        assert!((card.ease - START_EASE).abs() < 1e-9, "ease changed");
+
+        card.review(5, 22 * DAY);
+        assert_eq!(card.interval, 38);
+        assert!(card.ease > START_EASE, "perfect did not get easier");
+
+        // forgetting starts it over and makes it harder
+        card.review(1, 61 * DAY);
+        assert_eq!((card.repetitions, card.interval), (0, 1));
+        assert_eq!(card.due_at, 62 * DAY);
+        assert!(card.ease < START_EASE);
+
+        for _ in 0..10 {
+            card.review(0, 62 * DAY);
+        }
+
+        assert!((card.ease - MIN_EASE).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_word_source_from_str() {
+        assert_eq!(
+            "history".parse::<WordSource>().unwrap(),
+            WordSource::History
+        );
+        assert_eq!("cache".parse::<WordSource>().unwrap(), WordSource::Cache);
+        assert!("files".parse::<WordSource>().is_err());
+    }
+
+    #[test]
+    fn test_mask_word() {
+        assert_eq!(
+            mask_word("A bass, or basses, in the Bass range.", "bass"),
+            "A ___, or ___, in the ___ range."
+        );
+
+        // short words only hide themselves
+        assert_eq!(mask_word("to run a runner", "run"), "to ___ a runner");
+    }
+
+    #[test]
+    fn test_pick_cards() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let deck = Deck::open_at(&root_path.join("learn.db")).unwrap();
+
+        let mut due = Card::new("due", 0);
+        let mut later = Card::new("later", 0);
+
+        due.review(4, 0);
+        later.review(4, 10 * DAY);
+
+        deck.save(&due, 0).unwrap();
+        deck.save(&later, 10 * DAY).unwrap();
+
+        let words: Vec<String> = vec!["later", "New", "due", "new", "other"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let cards = pick_cards(&deck, &words, 3, 5 * DAY).unwrap();
+
+        let picked: Vec<&str> =
+            cards.iter().map(|card| card.word.as_str()).collect();
+
+        assert_eq!(picked, vec!["due", "new", "other"]);
+        assert_eq!(deck.next_due().unwrap(), Some(DAY));
+    }
+
+    #[test]
+    fn test_quiz() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+        let deck = Deck::open_at(&root_path.join("learn.db")).unwrap();
+
+        cache
+            .cache_definition("test", &[fake_word_data()], "test")
+            .unwrap();
+
+        let cards = vec![Card::new("missing", 0), Card::new("test", 0)];
+
+        let mut output = Vec::new();
+
+        let summary = quiz(
+            &cache,
+            &deck,
+            cards,
+            false,
+            Cursor::new("Test\n"),
+            &mut output,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            summary,
+            LearnSummary {
+                reviewed: 1,
+                remembered: 1
+            }
+        );
+        assert!(output.contains("___ definition"), "{}", output);
+        assert!(output.contains("right"), "{}", output);
+        assert!(!deck.contains("missing").unwrap());
+
+        let card = &deck.due(i64::MAX, 10).unwrap()[0];
+
+        assert_eq!((card.word.as_str(), card.interval), ("test", 1));
+
+        // the wrong number is asked again and the end of input stops it
+        let mut output = Vec::new();
+
+        let summary = quiz(
+            &cache,
+            &deck,
+            vec![card.clone(), card.clone()],
+            true,
+            Cursor::new("\n9\n2\n\n"),
+            &mut output,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            summary,
+            LearnSummary {
+                reviewed: 1,
+                remembered: 0
+            }
+        );
+        assert!(output.contains("test definition"), "{}", output);
+        assert!(output.contains("give a number from 0 to 5"), "{}", output);
+    }
+}
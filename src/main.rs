@@ -1,25 +1,224 @@
 mod actions;
+mod anki;
 mod cache;
 mod config;
 mod dict_api;
+mod dictd;
+mod error;
 mod formatter;
+mod history;
+mod learn;
+mod prefetch;
+mod provider;
 mod spell;
+mod stardict;
 mod utils;
+mod wiktionary;
+mod wordnet;
 
 #[cfg(test)]
 mod test_utils;
 
-use std::{error::Error, io};
+use std::{fs, io, process, time::Duration};
 
 use clap::Clap;
 
 use atty::Stream;
 
 use crate::{
-    actions::WordAction, config::get_user_config, formatter::FormatterConfig,
+    actions::{cache_command, history, search, spell, WordAction},
+    anki::{export_cards, CardSelection},
+    cache::{parse_age, Cache, CacheOptions, MergeMode},
+    config::get_user_config,
+    error::WordsError,
+    formatter::FormatterConfig,
+    history::{parse_since, parse_until},
+    learn::{learn, LearnOptions, WordSource},
+    prefetch::{prefetch, read_words, PrefetchOptions},
+    provider::ProviderConfig,
     utils::get_tty_cols,
+    wiktionary::import,
 };
 
+#[derive(Clap)]
+pub enum CacheCommand {
+    /// list the cached words
+    ///
+    /// each word is printed with where it came from, when it was fetched and
+    /// how many times it was looked up
+    List {
+        /// only list words matching a glob like `bas*`
+        #[clap(default_value = "*")]
+        pattern: String,
+    },
+    /// print the json cached for a word
+    Show { word: String },
+    /// remove a word from the cache
+    Rm { word: String },
+    /// remove words fetched a while ago
+    Prune {
+        /// how old a word has to be to be removed, like `30d` or `12h`
+        #[clap(long, parse(try_from_str = parse_age))]
+        older_than: Duration,
+    },
+    /// remove every word from the cache
+    Clear,
+    /// write every cached word to a JSONL file to share
+    ///
+    /// the file is gzipped if it ends in `.gz`
+    Export {
+        /// the file to write, `-` will write to stdout
+        path: String,
+    },
+    /// read words from a file made with `cache export`
+    Import {
+        /// the file to read, `-` will read from stdin
+        path: String,
+        /// what to do when a word is already cached, keep the newest one, the
+        /// existing one or always overwrite it
+        #[clap(
+            long,
+            default_value = "newest",
+            possible_values = &["newest", "existing", "overwrite"]
+        )]
+        merge: MergeMode,
+    },
+    /// upgrade every word saved by an older words_cli
+    ///
+    /// words are upgraded when they are read anyway, this does all of them at
+    /// once and sets aside any that cant be read
+    Migrate,
+    /// print how much is in the cache
+    ///
+    /// this is how many words are cached, how much space they take, the oldest
+    /// and newest words and the most looked up words
+    Stats,
+}
+
+#[derive(Clap)]
+pub enum WordsCommand {
+    /// import a wiktionary dump in to the cache
+    ///
+    /// this reads a Wiktextract JSONL dump or a raw Wiktionary XML dump, the
+    /// dump can be gzipped
+    Import {
+        /// the dump to import, `-` will read from stdin
+        path: String,
+        /// the format of the dump, this is guessed from the file name
+        #[clap(long, possible_values = &["jsonl", "xml"])]
+        format: Option<String>,
+        /// the language code of the words to import
+        #[clap(long, default_value = "en")]
+        lang: String,
+    },
+    /// fetch a list of words in to the cache to use offline
+    ///
+    /// words that are already cached are skipped
+    Prefetch {
+        /// a file with a word on each line, `-` will read from stdin
+        path: String,
+        /// how many words to fetch at once
+        #[clap(short, long, default_value = "4")]
+        jobs: usize,
+        /// the most requests to make a second
+        #[clap(long)]
+        rate: Option<f64>,
+    },
+    /// check the spelling of a list of words or of whole documents
+    ///
+    /// each word is printed with its suggestions, or `correct`, as soon as it
+    /// is checked, this is what `-s -` does with stdin
+    ///
+    /// with `--document` every word in the files is checked and each
+    /// misspelled one is printed with its line and column, finding any exits
+    /// with an error
+    Spell {
+        /// files with a word on each line, `-` will read from stdin
+        #[clap(required = true)]
+        paths: Vec<String>,
+        /// check every word in the files skipping code and links, like for a
+        /// README
+        #[clap(long)]
+        document: bool,
+    },
+    /// find cached words from what they mean
+    ///
+    /// this searches the definitions, examples and synonyms of every cached
+    /// word and does not need the network
+    Search {
+        /// the words to look for
+        phrase: String,
+        /// the most words to print
+        #[clap(short, long, default_value = "10")]
+        limit: usize,
+    },
+    /// print the words you looked up
+    ///
+    /// each lookup is printed with when it was made, if it was a definition
+    /// or suggestions, the query, the word that was found, where it came from
+    /// and how it went
+    History {
+        /// only lookups since a date like `2020-01-31` or an age like `2h`
+        #[clap(long, parse(try_from_str = parse_since))]
+        since: Option<i64>,
+        /// only lookups until a date like `2020-01-31` or an age like `2h`
+        #[clap(long, parse(try_from_str = parse_until))]
+        until: Option<i64>,
+        /// remove the lookups instead of printing them
+        #[clap(long)]
+        clear: bool,
+    },
+    /// learn the words you looked up with spaced repetition
+    ///
+    /// a definition is shown and you type the word, or with `--reverse` the
+    /// word is shown and you say how well you knew it, words you know come
+    /// back less often
+    Learn {
+        /// where to get new words, the words you looked up or every cached
+        /// word
+        #[clap(
+            long,
+            default_value = "history",
+            possible_values = &["history", "cache"]
+        )]
+        from: WordSource,
+        /// the most words to review at once
+        #[clap(short, long, default_value = "20")]
+        limit: usize,
+        /// show the word and ask for what it means
+        #[clap(short, long)]
+        reverse: bool,
+    },
+    /// write cached words as flash cards Anki can import
+    ///
+    /// the front of each card is the word and its phonetic and the back is
+    /// its definitions and examples, pick the words by name, with a glob or by
+    /// when they were looked up
+    Anki {
+        /// the words to make cards for
+        words: Vec<String>,
+        /// make cards for the cached words matching a glob like `bas*`
+        #[clap(long)]
+        pattern: Option<String>,
+        /// make cards for words looked up since a date like `2020-01-31` or
+        /// an age like `2h`
+        #[clap(long, parse(try_from_str = parse_since))]
+        since: Option<i64>,
+        /// make cards for words looked up until a date like `2020-01-31` or
+        /// an age like `2h`
+        #[clap(long, parse(try_from_str = parse_until))]
+        until: Option<i64>,
+        /// the tab separated file to write, `-` will write to stdout
+        #[clap(short, long, default_value = "-")]
+        output: String,
+    },
+    /// look at or clean up the cache
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+}
+
 #[derive(Clap)]
 #[clap(name = "words_cli")]
 /// a tool for words
@@ -46,6 +245,15 @@ pub struct WordArgs {
     /// print word definition
     #[clap(short, long, conflicts_with = "suggest")]
     pub define: Option<String>,
+    /// only print entry N when a word has more then one
+    #[clap(short, long, requires = "define")]
+    pub entry: Option<usize>,
+    /// print the databases the provider can look in
+    #[clap(long, conflicts_with_all = &["suggest", "define"])]
+    pub list_databases: bool,
+    /// print the strategies the provider can match words with
+    #[clap(long, conflicts_with_all = &["suggest", "define", "list-databases"])]
+    pub list_strategies: bool,
     /// columns to align definition text
     ///
     /// this will make the definition text stay within the specified columns
@@ -54,10 +262,49 @@ pub struct WordArgs {
     /// supply a config path
     #[clap(short = "C", long)]
     pub config: Option<String>,
+    /// dictionary provider to get definitions from
+    ///
+    /// this overrides the provider set in the config
+    #[clap(short, long)]
+    pub provider: Option<String>,
+    /// the dict database or stardict bookname to look in
+    ///
+    /// `*` will look in all of them and `!` will stop at the first match
+    #[clap(long)]
+    pub database: Option<String>,
+    /// the dict strategy used to match words when nothing is found
+    #[clap(long)]
+    pub strategy: Option<String>,
+    /// how many times to retry when the dictionary is rate limiting us
+    #[clap(long)]
+    pub retries: Option<u32>,
+    /// the dictionaryapi entries url, this can point at a mirror
+    #[clap(long)]
+    pub api_url: Option<String>,
+    /// the language code of the word to look up
+    #[clap(long)]
+    pub lang: Option<String>,
+    /// how many seconds to wait for the dictionary to respond
+    #[clap(long)]
+    pub timeout: Option<u64>,
+    /// the user agent to send to the dictionary
+    #[clap(long)]
+    pub user_agent: Option<String>,
+    /// a http or socks proxy to send requests through
+    #[clap(long)]
+    pub proxy: Option<String>,
+    /// fetch the definition again even if it is cached
+    #[clap(long, conflicts_with = "offline")]
+    pub refresh: bool,
+    /// never use the network, stale cached definitions are used instead
+    #[clap(long)]
+    pub offline: bool,
+    #[clap(subcommand)]
+    pub command: Option<WordsCommand>,
 }
 
 // read input from stdin if asked for
-fn get_from_stdin() -> Result<String, Box<dyn Error>> {
+fn get_from_stdin() -> Result<String, WordsError> {
     use std::io::Read;
 
     // get and lock stdin to read safely from stdin
@@ -66,10 +313,12 @@ fn get_from_stdin() -> Result<String, Box<dyn Error>> {
 
     let mut buf = String::new();
 
-    stdin.read_to_string(&mut buf)?;
+    stdin
+        .read_to_string(&mut buf)
+        .map_err(|e| WordsError::BadInput(format!("cant read stdin: {}", e)))?;
 
     if buf.is_empty() {
-        Err(Box::from("nothing in stdin"))
+        Err(WordsError::BadInput("nothing in stdin".to_string()))
     } else {
         let output = buf.trim().to_string();
 
@@ -77,30 +326,89 @@ fn get_from_stdin() -> Result<String, Box<dyn Error>> {
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let args = WordArgs::parse();
 
-    let word_action = WordAction::new(&args);
+    // print the kind of error with the message so its clear what went wrong
+    // and exit with a code for each kind so scripts can tell them apart
+    if let Err(err) = run(args) {
+        eprintln!("words_cli: {}: {}", err.kind(), err);
 
+        process::exit(err.exit_code());
+    }
+}
+
+fn run(args: WordArgs) -> Result<(), WordsError> {
     let user_config = get_user_config(args.config.as_ref())?;
 
-    let mut config = match user_config {
-        None => FormatterConfig::default(),
-        Some(mut val) => val.resolve_config(),
+    let (mut config, mut provider_config, mut cache_options) = match user_config
+    {
+        None => (
+            FormatterConfig::default(),
+            ProviderConfig::default(),
+            CacheOptions::default(),
+        ),
+        Some(mut val) => (
+            val.resolve_config(),
+            val.resolve_provider_config(),
+            val.resolve_cache_options(),
+        ),
     };
 
     // override default settings with the cli options
     config.print = !args.no_print;
 
+    if let Some(provider) = args.provider.as_ref() {
+        provider_config.provider = provider.to_owned();
+    }
+
+    if let Some(database) = args.database.as_ref() {
+        provider_config.database = database.to_owned();
+    }
+
+    if let Some(strategy) = args.strategy.as_ref() {
+        provider_config.strategy = strategy.to_owned();
+    }
+
+    if let Some(retries) = args.retries {
+        provider_config.retries = retries;
+    }
+
+    let request_opts = &mut provider_config.request_opts;
+
+    if let Some(api_url) = args.api_url.as_ref() {
+        request_opts.url = api_url.to_owned();
+    }
+
+    if let Some(lang) = args.lang.as_ref() {
+        request_opts.lang = lang.to_owned();
+    }
+
+    if let Some(timeout) = args.timeout {
+        request_opts.timeout = Duration::from_secs(timeout);
+    }
+
+    if let Some(user_agent) = args.user_agent.as_ref() {
+        request_opts.user_agent = user_agent.to_owned();
+    }
+
+    if let Some(proxy) = args.proxy.as_ref() {
+        request_opts.proxy = Some(proxy.to_owned());
+    }
+
+    cache_options.refresh = args.refresh;
+    cache_options.offline = args.offline;
+
     if args.no_formatting {
         config.formatting = false;
 
         config.clear_formating();
     } else {
+        // fall back to the usual terminal width if tput cant tell us
         config.columns = if let Some(columns) = args.columns {
             columns
         } else {
-            get_tty_cols()
+            get_tty_cols().unwrap_or(80)
         };
     }
 
@@ -110,5 +418,142 @@ fn main() -> Result<(), Box<dyn Error>> {
         config.clear_style();
     }
 
-    word_action.run(&config)
+    // where looked up words are cached, a word in another language or from
+    // another provider is a different word
+    let scope = provider_config.cache_scope();
+
+    match args.command {
+        Some(WordsCommand::Import {
+            ref path,
+            ref format,
+            ref lang,
+        }) => {
+            let count = import(path, format.as_deref(), lang)?;
+
+            if count.skipped > 0 {
+                eprintln!("words_cli: skipped {} lines", count.skipped);
+            }
+
+            if config.print {
+                println!(
+                    "imported {} words, replaced {} cached words",
+                    count.words, count.replaced
+                );
+            }
+
+            Ok(())
+        }
+        Some(WordsCommand::Prefetch {
+            ref path,
+            jobs,
+            rate,
+        }) => {
+            let words = if path == "-" {
+                let stdin = io::stdin();
+
+                read_words(stdin.lock())?
+            } else {
+                let file = fs::File::open(path).map_err(|e| {
+                    WordsError::BadInput(format!("cant open {}: {}", path, e))
+                })?;
+
+                read_words(io::BufReader::new(file))?
+            };
+
+            let summary = prefetch(
+                &Cache::open()?.with_scope(scope),
+                &provider_config,
+                words,
+                &PrefetchOptions {
+                    jobs,
+                    rate,
+                    not_found_ttl: cache_options.not_found_ttl,
+                },
+                config.print,
+            )?;
+
+            if config.print {
+                println!(
+                    "fetched {}, not found {}, failed {}, already cached {}",
+                    summary.fetched,
+                    summary.not_found,
+                    summary.failed,
+                    summary.cached
+                );
+            }
+
+            Ok(())
+        }
+        Some(WordsCommand::Spell {
+            ref paths,
+            document,
+        }) => spell(paths, document, &config),
+        Some(WordsCommand::Search { ref phrase, limit }) => {
+            search(phrase, limit, scope, &config)
+        }
+        Some(WordsCommand::History {
+            since,
+            until,
+            clear,
+        }) => history(since, until, clear, &config),
+        Some(WordsCommand::Learn {
+            from,
+            limit,
+            reverse,
+        }) => {
+            let stdin = io::stdin();
+
+            let summary = learn(
+                &LearnOptions {
+                    source: from,
+                    limit,
+                    reverse,
+                    scope,
+                },
+                stdin.lock(),
+                io::stdout(),
+            )?;
+
+            if config.print && summary.reviewed > 0 {
+                println!(
+                    "\nreviewed {}, remembered {}",
+                    summary.reviewed, summary.remembered
+                );
+            }
+
+            Ok(())
+        }
+        Some(WordsCommand::Anki {
+            ref words,
+            ref pattern,
+            since,
+            until,
+            ref output,
+        }) => {
+            let selection = CardSelection {
+                words: words.to_owned(),
+                pattern: pattern.to_owned(),
+                since,
+                until,
+                scope,
+            };
+
+            let written = export_cards(&selection, output)?;
+
+            // stdout is the cards so dont print anything else there
+            if config.print && output != "-" {
+                println!("wrote {} cards", written);
+            }
+
+            Ok(())
+        }
+        Some(WordsCommand::Cache { ref command }) => {
+            cache_command(command, scope, &config)
+        }
+        None => WordAction::new(&args)?.run(
+            &config,
+            &provider_config,
+            &cache_options,
+        ),
+    }
 }
@@ -0,0 +1,291 @@
+//! fetch a list of words in to the cache ahead of time
+//!
+//! the words are fetched by a few threads at once, each with its own
+//! provider, and sent back to be cached so only one thread writes to the
+//! cache, the requests can be capped to a rate so the dictionary does not
+//! start rate limiting us
+use std::{
+    collections::HashSet,
+    io::BufRead,
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cache::{cache_key, Cache},
+    dict_api::WordData,
+    error::WordsError,
+    provider::{make_provider, ProviderConfig},
+};
+
+/// how to prefetch
+///
+/// `jobs` is how many words to fetch at once
+///
+/// `rate` is the most requests to make a second, none for no limit
+///
+/// `not_found_ttl` is how long a word that was not found is skipped for
+pub struct PrefetchOptions {
+    pub jobs: usize,
+    pub rate: Option<f64>,
+    pub not_found_ttl: Duration,
+}
+
+/// what happened to the words in the list
+#[derive(Debug, Default, PartialEq)]
+pub struct PrefetchSummary {
+    pub fetched: usize,
+    pub not_found: usize,
+    pub failed: usize,
+    pub cached: usize,
+}
+
+// the longest to wait between requests, a slower rate is rounded up to this
+const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// spaces requests out so no more then one is started every interval
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64) -> Self {
+        Self {
+            interval: Duration::try_from_secs_f64(1.0 / rate)
+                .unwrap_or(MAX_INTERVAL)
+                .min(MAX_INTERVAL),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    // wait for a turn to make a request
+    fn wait(&self) {
+        let wait_until = {
+            let mut next = self.next.lock().unwrap();
+
+            let now = Instant::now();
+
+            let wait_until = (*next).max(now);
+
+            *next = wait_until + self.interval;
+
+            wait_until
+        };
+
+        let now = Instant::now();
+
+        if wait_until > now {
+            thread::sleep(wait_until - now);
+        }
+    }
+}
+
+/// read a word from every line skipping blank lines and words that are
+/// already in the list
+pub fn read_words<R: BufRead>(reader: R) -> Result<Vec<String>, WordsError> {
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| {
+            WordsError::BadInput(format!("cant read the word list: {}", e))
+        })?;
+
+        let word = line.trim();
+
+        if !word.is_empty() && seen.insert(cache_key(word)) {
+            words.push(word.to_string());
+        }
+    }
+
+    Ok(words)
+}
+
+/// fetch every word that is not cached and cache it, words the dictionary
+/// does not have are cached as not found and not fetched again until that
+/// is older then the not found ttl
+///
+/// if `progress` is set a line is printed to stderr for every word
+pub fn prefetch(
+    cache: &Cache,
+    provider_conf: &ProviderConfig,
+    words: Vec<String>,
+    opts: &PrefetchOptions,
+    progress: bool,
+) -> Result<PrefetchSummary, WordsError> {
+    if opts.jobs == 0 {
+        return Err(WordsError::BadInput("need at least one job".to_string()));
+    }
+
+    let limiter = match opts.rate {
+        Some(rate) if rate > 0.0 && rate.is_finite() => {
+            Some(RateLimiter::new(rate))
+        }
+        Some(rate) => {
+            return Err(WordsError::BadInput(format!(
+                "{} is not a rate, it needs to be more then zero",
+                rate
+            )))
+        }
+        None => None,
+    };
+
+    let mut summary = PrefetchSummary::default();
+
+    let mut to_fetch = Vec::new();
+
+    for word in words {
+        let not_found = cache
+            .get_not_found(&word)?
+            .is_some_and(|not_found| !not_found.is_stale(opts.not_found_ttl));
+
+        if not_found || cache.contains(&word)? {
+            summary.cached += 1;
+        } else {
+            to_fetch.push(word);
+        }
+    }
+
+    let total = to_fetch.len();
+
+    // the threads take words from the end
+    to_fetch.reverse();
+
+    let queue = Mutex::new(to_fetch);
+
+    thread::scope(|scope| -> Result<(), WordsError> {
+        // the receiver is dropped if saving fails so the threads stop at
+        // their next word instead of fetching the rest of the list
+        let (sender, receiver) = mpsc::channel();
+
+        for _ in 0..opts.jobs.min(total) {
+            let sender = sender.clone();
+            let queue = &queue;
+            let limiter = limiter.as_ref();
+
+            scope.spawn(move || {
+                let provider = make_provider(provider_conf);
+
+                loop {
+                    let word = match queue.lock().unwrap().pop() {
+                        Some(word) => word,
+                        None => break,
+                    };
+
+                    if let Some(limiter) = limiter {
+                        limiter.wait();
+                    }
+
+                    let result = match provider.as_ref() {
+                        Ok(provider) => provider.get_definition(&word),
+                        Err(err) => Err(WordsError::Config(err.to_string())),
+                    };
+
+                    if sender.send((word, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // the threads hold the only senders now so this ends when they do
+        drop(sender);
+
+        for (done, (word, result)) in receiver.iter().enumerate() {
+            let outcome = save_result(cache, provider_conf, &word, result)?;
+
+            match outcome {
+                Outcome::Fetched => summary.fetched += 1,
+                Outcome::NotFound => summary.not_found += 1,
+                Outcome::Failed(_) => summary.failed += 1,
+            }
+
+            if progress {
+                eprintln!("[{}/{}] {}: {}", done + 1, total, word, outcome);
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(summary)
+}
+
+enum Outcome {
+    Fetched,
+    NotFound,
+    Failed(WordsError),
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Fetched => write!(f, "fetched"),
+            Outcome::NotFound => write!(f, "not found"),
+            Outcome::Failed(err) => write!(f, "{}: {}", err.kind(), err),
+        }
+    }
+}
+
+// cache what came back for a word
+fn save_result(
+    cache: &Cache,
+    provider_conf: &ProviderConfig,
+    word: &str,
+    result: Result<Vec<WordData>, WordsError>,
+) -> Result<Outcome, WordsError> {
+    let outcome = match result {
+        Ok(entries) => {
+            cache.cache_definition(word, &entries, &provider_conf.provider)?;
+
+            Outcome::Fetched
+        }
+        Err(WordsError::NotFound(message)) => {
+            cache.cache_not_found(word, &message, &provider_conf.provider)?;
+
+            Outcome::NotFound
+        }
+        Err(err) => Outcome::Failed(err),
+    };
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_words() {
+        let list = "bass\n\n  lead \nBass\nmonster\n";
+
+        let words = read_words(list.as_bytes()).unwrap();
+
+        assert_eq!(words, vec!["bass", "lead", "monster"]);
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(20.0);
+
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            limiter.wait();
+        }
+
+        // the first request goes right away and the rest wait a turn
+        assert!(
+            start.elapsed() >= Duration::from_millis(200),
+            "did not space out the requests"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_slow_rate() {
+        assert_eq!(RateLimiter::new(1e-300).interval, MAX_INTERVAL);
+        assert_eq!(RateLimiter::new(f64::MIN_POSITIVE).interval, MAX_INTERVAL);
+    }
+}
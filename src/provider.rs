@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use crate::{
+    cache::CacheScope,
+    dict_api::{DictionaryApi, RequestOptions, RetryPolicy, WordData},
+    dictd::Dictd,
+    error::WordsError,
+    stardict::{default_stardict_path, StarDict},
+    wordnet::{default_wordnet_path, WordNet},
+};
+
+/// a source of word definitions
+pub trait DictionaryProvider {
+    /// look up the query and turn the result in to WordData, there can be
+    /// more then one entry for words like "bass" that have different
+    /// meanings and origins
+    fn get_definition(&self, query: &str) -> Result<Vec<WordData>, WordsError>;
+
+    /// the databases the provider can look in and there descriptions
+    fn databases(&self) -> Result<Vec<(String, String)>, WordsError> {
+        Err(WordsError::BadInput(
+            "this provider does not have databases".to_string(),
+        ))
+    }
+
+    /// the strategies the provider can match words with and there
+    /// descriptions
+    fn strategies(&self) -> Result<Vec<(String, String)>, WordsError> {
+        Err(WordsError::BadInput(
+            "this provider does not have match strategies".to_string(),
+        ))
+    }
+}
+
+/// which provider to use and how to set it up
+///
+///
+/// `provider` is the name of the provider to get definitions from
+///
+/// default: "dictionaryapi"
+///
+/// `wordnet_path` is the WordNet database directory for the wordnet provider
+///
+/// default: WNSEARCHDIR or "/usr/share/wordnet"
+///
+/// `stardict_path` is the directory with the bundles for the stardict provider
+///
+/// default: "~/.stardict/dic" or "/usr/share/stardict/dic"
+///
+/// `dictd_host` and `dictd_port` are where the dict server is for the dictd
+/// provider
+///
+/// default: "localhost" and 2628
+///
+/// `database` is the dict database or stardict bookname to look in, `*` for
+/// all of them and `!` for the first one with a match
+///
+/// default: "*"
+///
+/// `strategy` is the dict match strategy used to suggest words
+///
+/// default: "." the servers default strategy
+///
+/// `retries` is how many times to retry when dictionaryapi is rate limiting
+/// us or having problems
+///
+/// default: 3
+///
+/// `request_opts` is how to talk to dictionaryapi
+///
+/// default: dictionaryapi.dev in english
+pub struct ProviderConfig {
+    pub provider: String,
+    pub wordnet_path: Option<PathBuf>,
+    pub stardict_path: Option<PathBuf>,
+    pub dictd_host: String,
+    pub dictd_port: u16,
+    pub database: String,
+    pub strategy: String,
+    pub retries: u32,
+    pub request_opts: RequestOptions,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            provider: "dictionaryapi".to_string(),
+            wordnet_path: None,
+            stardict_path: None,
+            dictd_host: "localhost".to_string(),
+            dictd_port: 2628,
+            database: "*".to_string(),
+            strategy: ".".to_string(),
+            retries: 3,
+            request_opts: RequestOptions::default(),
+        }
+    }
+}
+
+impl ProviderConfig {
+    /// the part of the cache words from this provider are saved in, a
+    /// dictionaryapi mirror at another url has the same words so it shares
+    /// the usual ones
+    ///
+    /// dictd and stardict give other words for each database, and dictd
+    /// suggests other words for each strategy, so those are part of the scope
+    /// when they are not the defaults, like `dictd/gcide/lev`
+    pub fn cache_scope(&self) -> CacheScope {
+        let defaults = ProviderConfig::default();
+
+        let provider = match self.provider.as_str() {
+            "dictd"
+                if self.database != defaults.database
+                    || self.strategy != defaults.strategy =>
+            {
+                format!("dictd/{}/{}", self.database, self.strategy)
+            }
+            "stardict" if self.database != defaults.database => {
+                format!("stardict/{}", self.database)
+            }
+            provider => provider.to_string(),
+        };
+
+        CacheScope {
+            provider,
+            lang: self.request_opts.lang.clone(),
+        }
+    }
+}
+
+/// make the provider named in the config
+pub fn make_provider(
+    config: &ProviderConfig,
+) -> Result<Box<dyn DictionaryProvider>, WordsError> {
+    match config.provider.as_str() {
+        "dictionaryapi" => Ok(Box::new(DictionaryApi::new(
+            config.request_opts.clone(),
+            RetryPolicy {
+                max_retries: config.retries,
+                ..RetryPolicy::default()
+            },
+        ))),
+        "wordnet" => {
+            let path = config
+                .wordnet_path
+                .clone()
+                .unwrap_or_else(default_wordnet_path);
+
+            Ok(Box::new(WordNet::new(path)))
+        }
+        "stardict" => {
+            let path = config
+                .stardict_path
+                .clone()
+                .unwrap_or_else(default_stardict_path);
+
+            Ok(Box::new(StarDict::new(path, &config.database)))
+        }
+        "dictd" => Ok(Box::new(Dictd::new(
+            &config.dictd_host,
+            config.dictd_port,
+            &config.database,
+            &config.strategy,
+        ))),
+        name => Err(WordsError::Config(format!("unknown provider: {}", name))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_make_provider_default() {
+        let config = ProviderConfig::default();
+
+        assert!(
+            make_provider(&config).is_ok(),
+            "could not make the default provider"
+        );
+    }
+
+    #[test]
+    fn test_cache_scope() {
+        let scope = |provider: &str, database: &str, strategy: &str| {
+            ProviderConfig {
+                provider: provider.to_string(),
+                database: database.to_string(),
+                strategy: strategy.to_string(),
+                ..Default::default()
+            }
+            .cache_scope()
+            .provider
+        };
+
+        assert_eq!(scope("dictd", "*", "."), "dictd");
+        assert_eq!(scope("dictd", "wn", "."), "dictd/wn/.");
+        assert_eq!(scope("dictd", "*", "lev"), "dictd/*/lev");
+        assert_eq!(scope("stardict", "WordNet", "lev"), "stardict/WordNet");
+        assert_eq!(scope("wordnet", "wn", "lev"), "wordnet");
+    }
+
+    #[test]
+    fn test_make_provider_unknown() {
+        let config = ProviderConfig {
+            provider: "not a provider".to_string(),
+            ..Default::default()
+        };
+
+        match make_provider(&config) {
+            Ok(_) => panic!("made a provider that does not exist"),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "unknown provider: not a provider",
+                "got a different error then expected"
+            ),
+        }
+    }
+}
@@ -1,39 +1,196 @@
-use std::error::Error;
+use std::io::BufRead;
 
-use ispell::SpellLauncher;
+use ispell::{SpellChecker, SpellLauncher};
 
-pub fn check_spelling(
-    query: &str,
-) -> Result<Option<Vec<String>>, Box<dyn Error>> {
-    let mut checker = SpellLauncher::new().aspell().launch()?;
+use crate::error::WordsError;
 
-    let spell_errs = checker.check(&query)?;
+/// a misspelled word in a document, the line and column count from one
+#[derive(Debug, PartialEq)]
+pub struct Misspelling {
+    pub line: usize,
+    pub column: usize,
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+/// a running aspell
+///
+/// starting aspell is slow so a list of words should all be checked with one
+/// of these instead of calling `check_spelling` for each
+pub struct Speller {
+    checker: SpellChecker,
+}
 
-    if spell_errs.is_empty() {
-        return Ok(None);
+impl Speller {
+    pub fn launch() -> Result<Self, WordsError> {
+        let checker = SpellLauncher::new().aspell().launch().map_err(|e| {
+            WordsError::SpellChecker(format!("cant start aspell: {}", e))
+        })?;
+
+        Ok(Self { checker })
     }
 
-    let mut output = Vec::new();
+    /// the suggestions for a query or none if it is spelled right
+    pub fn check(
+        &mut self,
+        query: &str,
+    ) -> Result<Option<Vec<String>>, WordsError> {
+        let spell_errs = self.checker.check(query)?;
 
-    for se in spell_errs {
-        if se.suggestions.is_empty() {
-            return Err(Box::from(format!("no suggestion for {}", query)));
+        if spell_errs.is_empty() {
+            return Ok(None);
         }
 
-        for suggestion in se.suggestions {
-            output.push(suggestion);
+        let mut output = Vec::new();
+
+        for se in spell_errs {
+            if se.suggestions.is_empty() {
+                return Err(WordsError::NotFound(format!(
+                    "no suggestion for {}",
+                    query
+                )));
+            }
+
+            for suggestion in se.suggestions {
+                output.push(suggestion);
+            }
         }
+
+        Ok(Some(output))
     }
 
-    Ok(Some(output))
+    /// the misspelled words in every line of a document
+    ///
+    /// fenced code blocks, inline code and links are skipped so this can be
+    /// run over a README
+    pub fn check_document<R: BufRead>(
+        &mut self,
+        input: R,
+    ) -> Result<Vec<Misspelling>, WordsError> {
+        let mut misspellings = Vec::new();
+        let mut in_code_block = false;
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.map_err(|e| {
+                WordsError::BadInput(format!("cant read the document: {}", e))
+            })?;
+
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+
+                continue;
+            }
+
+            if in_code_block {
+                continue;
+            }
+
+            let text = prose(&line);
+
+            for spell_err in self.checker.check(&text)? {
+                misspellings.push(Misspelling {
+                    line: index + 1,
+                    column: find_column(
+                        &text,
+                        &spell_err.misspelled,
+                        spell_err.position,
+                    ),
+                    word: spell_err.misspelled,
+                    suggestions: spell_err.suggestions,
+                });
+            }
+        }
+
+        Ok(misspellings)
+    }
+}
+
+// blank out the parts of a line that are not words to check, inline code and
+// links, every other character stays where it is so the columns line up
+fn prose(line: &str) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut in_code = false;
+    let mut in_link = false;
+
+    for (index, c) in line.char_indices() {
+        if c == '`' && !in_link {
+            in_code = !in_code;
+        } else if !in_code
+            && (line[index..].starts_with("http://")
+                || line[index..].starts_with("https://"))
+        {
+            in_link = true;
+        } else if c.is_whitespace() || matches!(c, ')' | '>' | ']') {
+            in_link = false;
+        }
+
+        if in_code || in_link || c == '`' {
+            output.push(' ');
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
+
+// where a word starts in a line counting characters from one
+//
+// aspell gives the offset in characters or in bytes depending on the version
+// and encoding so look for the word at either one, falling back to the first
+// place it is found
+fn find_column(line: &str, word: &str, position: usize) -> usize {
+    let columns: Vec<(usize, usize)> = line
+        .match_indices(word)
+        .map(|(byte, _)| (byte, line[..byte].chars().count()))
+        .collect();
+
+    columns
+        .iter()
+        .find(|(byte, chars)| *chars == position || *byte == position)
+        .or_else(|| columns.first())
+        .map_or(position + 1, |(_, chars)| chars + 1)
+}
+
+pub fn check_spelling(query: &str) -> Result<Option<Vec<String>>, WordsError> {
+    Speller::launch()?.check(query)
 }
 
 // TODO: this will break if when spelling source changes and when the spell
 // checkers change what they return
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_prose() {
+        let line = "see `cargo tset` at https://exmple.com/a) or <http://b>.";
+
+        assert_eq!(
+            prose(line),
+            "see              at                     ) or <        >."
+        );
+        assert_eq!(prose(line).chars().count(), line.chars().count());
+
+        assert_eq!(prose("plain wrods"), "plain wrods");
+    }
+
+    #[test]
+    fn test_find_column() {
+        // characters
+        assert_eq!(find_column("héllo wrld", "wrld", 6), 7);
+        // bytes
+        assert_eq!(find_column("héllo wrld", "wrld", 7), 7);
+        // the second one
+        assert_eq!(find_column("teh cat, teh dog", "teh", 9), 10);
+        // aspell is somewhere else
+        assert_eq!(find_column("a teh", "teh", 0), 3);
+        assert_eq!(find_column("a b", "teh", 4), 5);
+    }
+
     #[test]
     fn test_check_spelling_bad_word() {
         let query = "flgrent";
@@ -0,0 +1,1110 @@
+//! read definitions from StarDict dictionary bundles
+//!
+//! a bundle is an `.ifo` file with the dictionary info, an `.idx` file with a
+//! sorted list of words and where there data is and a `.dict` file with the
+//! data, the `.idx` can be gzipped and the `.dict` can be dictzipped
+//!
+//! the bundles are indexed the first time a word is looked up and only the
+//! bytes for the entries that match are read from the dict file after that
+use std::{
+    cell::OnceCell,
+    cmp::Ordering,
+    env, fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, Decompress, FlushDecompress};
+
+use crate::{
+    dict_api::{Definition, Meaning, Phonetic, WordData},
+    error::WordsError,
+    provider::DictionaryProvider,
+};
+
+/// a word in the index and where its data is in the dict file
+struct IndexEntry {
+    word: String,
+    offset: u64,
+    size: usize,
+}
+
+/// how to get at the data in the dict file
+enum DictFile {
+    /// a plain `.dict` that can be read from anywhere
+    Plain(PathBuf),
+    /// a `.dict.dz` made of chunks that are each `chunk_len` bytes once
+    /// inflated, `chunks` has where each chunk starts in the file and where
+    /// the last one ends
+    DictZip {
+        path: PathBuf,
+        chunk_len: u64,
+        chunks: Vec<u64>,
+    },
+    /// a gzipped dict with out a chunk table so it has to be inflated all at
+    /// once
+    Inflated(Vec<u8>),
+}
+
+impl DictFile {
+    fn open(path: PathBuf) -> Result<Self, WordsError> {
+        if path.extension().is_none_or(|ext| ext != "dz") {
+            return Ok(DictFile::Plain(path));
+        }
+
+        let mut file =
+            fs::File::open(&path).map_err(|e| read_error(&path, e))?;
+
+        // the gzip header can have a file name and comment after the extra
+        // field so read a good amount of the start to find the data
+        let mut header = Vec::new();
+
+        file.by_ref()
+            .take(64 * 1024)
+            .read_to_end(&mut header)
+            .map_err(|e| read_error(&path, e))?;
+
+        match parse_dictzip_header(&header) {
+            Some((chunk_len, chunks)) => Ok(DictFile::DictZip {
+                path,
+                chunk_len,
+                chunks,
+            }),
+            None => Ok(DictFile::Inflated(
+                read_maybe_gz(&path).map_err(|e| read_error(&path, e))?,
+            )),
+        }
+    }
+
+    /// read `size` bytes of the inflated data starting at `offset`
+    fn read(&self, offset: u64, size: usize) -> io::Result<Vec<u8>> {
+        let out_of_bounds = || {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "entry is out of bounds",
+            )
+        };
+
+        match self {
+            DictFile::Plain(path) => {
+                let mut file = fs::File::open(path)?;
+                let mut data = vec![0; size];
+
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut data)?;
+
+                Ok(data)
+            }
+            DictFile::DictZip {
+                path,
+                chunk_len,
+                chunks,
+            } => {
+                if size == 0 {
+                    return Ok(Vec::new());
+                }
+
+                let first = (offset / chunk_len) as usize;
+                let last = ((offset + size as u64 - 1) / chunk_len) as usize;
+
+                if last + 1 >= chunks.len() {
+                    return Err(out_of_bounds());
+                }
+
+                let mut file = fs::File::open(path)?;
+                let mut compressed =
+                    vec![0; (chunks[last + 1] - chunks[first]) as usize];
+
+                file.seek(SeekFrom::Start(chunks[first]))?;
+                file.read_exact(&mut compressed)?;
+
+                let mut inflated = Vec::new();
+
+                // every chunk ends with a full flush so each one can be
+                // inflated on its own
+                for chunk in first..=last {
+                    let start = (chunks[chunk] - chunks[first]) as usize;
+                    let end = (chunks[chunk + 1] - chunks[first]) as usize;
+
+                    let mut inflater = Decompress::new(false);
+
+                    inflated.reserve(*chunk_len as usize);
+
+                    inflater
+                        .decompress_vec(
+                            &compressed[start..end],
+                            &mut inflated,
+                            FlushDecompress::Sync,
+                        )
+                        .map_err(|e| {
+                            io::Error::new(io::ErrorKind::InvalidData, e)
+                        })?;
+                }
+
+                let start = (offset - first as u64 * chunk_len) as usize;
+
+                inflated
+                    .get(start..start + size)
+                    .map(|data| data.to_vec())
+                    .ok_or_else(out_of_bounds)
+            }
+            DictFile::Inflated(dict) => {
+                let start = offset as usize;
+
+                dict.get(start..start + size)
+                    .map(|data| data.to_vec())
+                    .ok_or_else(out_of_bounds)
+            }
+        }
+    }
+}
+
+/// a dictionary made from the `.ifo`, `.idx` and `.dict` files
+struct Bundle {
+    bookname: String,
+    word_count: usize,
+    same_type_sequence: Option<String>,
+    index: Vec<IndexEntry>,
+    dict: DictFile,
+}
+
+impl Bundle {
+    fn open(ifo_path: &Path) -> Result<Self, WordsError> {
+        let ifo = fs::read_to_string(ifo_path)
+            .map_err(|e| read_error(ifo_path, e))?;
+
+        let mut lines = ifo.lines();
+
+        if lines.next().map(|l| l.trim()) != Some("StarDict's dict ifo file") {
+            return Err(WordsError::Parse(format!(
+                "not a StarDict ifo file: {}",
+                ifo_path.display()
+            )));
+        }
+
+        let mut bookname = String::new();
+        let mut word_count = 0;
+        let mut offset_bits = 32;
+        let mut same_type_sequence = None;
+
+        for line in lines {
+            let mut key_val = line.splitn(2, '=');
+
+            let (key, val) = match (key_val.next(), key_val.next()) {
+                (Some(key), Some(val)) => (key.trim(), val.trim()),
+                _ => continue,
+            };
+
+            match key {
+                "bookname" => bookname = val.to_string(),
+                "wordcount" => word_count = parse_number(ifo_path, key, val)?,
+                "idxoffsetbits" => {
+                    offset_bits = parse_number(ifo_path, key, val)?
+                }
+                "sametypesequence" => {
+                    same_type_sequence = Some(val.to_string())
+                }
+                _ => {}
+            }
+        }
+
+        let idx_bytes = match find_with_ext(ifo_path, &["idx", "idx.gz"]) {
+            Some(idx_path) => read_maybe_gz(&idx_path)
+                .map_err(|e| read_error(&idx_path, e))?,
+            None => {
+                return Err(WordsError::Config(format!(
+                    "no idx file for {}",
+                    ifo_path.display()
+                )))
+            }
+        };
+
+        let dict_path = find_with_ext(ifo_path, &["dict", "dict.dz"])
+            .ok_or_else(|| {
+                WordsError::Config(format!(
+                    "no dict file for {}",
+                    ifo_path.display()
+                ))
+            })?;
+
+        Ok(Self {
+            bookname,
+            word_count,
+            same_type_sequence,
+            index: parse_index(&idx_bytes, offset_bits)?,
+            dict: DictFile::open(dict_path)?,
+        })
+    }
+
+    /// binary search the index for every entry matching the word
+    fn lookup(&self, word: &str) -> &[IndexEntry] {
+        // the index is sorted case insensitively so find the first entry that
+        // is not less then the word ignoring case
+        let start = self.index.partition_point(|entry| {
+            ascii_casecmp(&entry.word, word) == Ordering::Less
+        });
+
+        let end = start
+            + self.index[start..]
+                .iter()
+                .take_while(|entry| {
+                    ascii_casecmp(&entry.word, word) == Ordering::Equal
+                })
+                .count();
+
+        &self.index[start..end]
+    }
+
+    fn read_data(
+        &self,
+        entries: &[IndexEntry],
+    ) -> Result<Vec<Vec<u8>>, WordsError> {
+        entries
+            .iter()
+            .map(|entry| {
+                self.dict.read(entry.offset, entry.size).map_err(|e| {
+                    WordsError::Parse(format!(
+                        "cant read the data for {}: {}",
+                        entry.word, e
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+/// get definitions from the StarDict bundles in a directory
+pub struct StarDict {
+    path: PathBuf,
+    database: String,
+    bundles: OnceCell<Vec<Bundle>>,
+}
+
+impl StarDict {
+    pub fn new(path: PathBuf, database: &str) -> Self {
+        Self {
+            path,
+            database: database.to_string(),
+            bundles: OnceCell::new(),
+        }
+    }
+
+    /// the bundles to look in, they are only opened the first time and a
+    /// bundle that cant be opened is skipped with a warning
+    fn bundles(&self) -> Result<&[Bundle], WordsError> {
+        if let Some(bundles) = self.bundles.get() {
+            return Ok(bundles);
+        }
+
+        let mut ifo_paths = Vec::new();
+
+        find_ifo_files(&self.path, &mut ifo_paths)
+            .map_err(|e| read_error(&self.path, e))?;
+
+        ifo_paths.sort();
+
+        let mut bundles = Vec::new();
+
+        for ifo_path in ifo_paths {
+            let bundle = match Bundle::open(&ifo_path) {
+                Ok(bundle) => bundle,
+                Err(err) => {
+                    eprintln!(
+                        "words_cli: skipping {}: {}",
+                        ifo_path.display(),
+                        err
+                    );
+
+                    continue;
+                }
+            };
+
+            if self.database == "*"
+                || self.database == "!"
+                || self.database == bundle.bookname
+            {
+                bundles.push(bundle);
+            }
+        }
+
+        Ok(self.bundles.get_or_init(|| bundles))
+    }
+}
+
+impl DictionaryProvider for StarDict {
+    fn get_definition(&self, query: &str) -> Result<Vec<WordData>, WordsError> {
+        let query = query.trim();
+
+        let mut word = None;
+        let mut phonetics = Vec::new();
+        let mut meanings = Vec::new();
+
+        for bundle in self.bundles()? {
+            let entries = bundle.lookup(query);
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            // prefer the headword with the same case as the query
+            let headword = entries
+                .iter()
+                .find(|entry| entry.word == query)
+                .unwrap_or(&entries[0]);
+
+            word.get_or_insert_with(|| headword.word.clone());
+
+            let mut definitions = Vec::new();
+
+            for data in bundle.read_data(entries)? {
+                let fields =
+                    parse_data(&data, bundle.same_type_sequence.as_deref());
+
+                for (field_type, text) in fields {
+                    match field_type {
+                        't' | 'y' => phonetics.push(Phonetic {
+                            text,
+                            audio: String::new(),
+                        }),
+                        'm' | 'l' | 'h' | 'g' | 'x' | 'k' | 'w' => {
+                            let text = if field_type == 'm' || field_type == 'l'
+                            {
+                                text
+                            } else {
+                                strip_markup(&text)
+                            };
+
+                            definitions.extend(
+                                text.lines()
+                                    .map(|line| line.trim())
+                                    .filter(|line| !line.is_empty())
+                                    .map(|line| Definition {
+                                        definition: line.to_string(),
+                                        example: None,
+                                        synonyms: None,
+                                        antonyms: None,
+                                    }),
+                            );
+                        }
+                        // sounds, pictures and resources cant be shown
+                        _ => {}
+                    }
+                }
+            }
+
+            meanings.push(Meaning {
+                partOfSpeech: bundle.bookname.clone(),
+                definitions,
+            });
+
+            if self.database == "!" {
+                break;
+            }
+        }
+
+        match word {
+            Some(word) => Ok(vec![WordData {
+                word,
+                phonetics,
+                meanings,
+                etymology: None,
+            }]),
+            None => {
+                Err(WordsError::NotFound("No Definitions Found".to_string()))
+            }
+        }
+    }
+
+    fn databases(&self) -> Result<Vec<(String, String)>, WordsError> {
+        Ok(self
+            .bundles()?
+            .iter()
+            .map(|bundle| {
+                (
+                    bundle.bookname.clone(),
+                    format!("{} words", bundle.word_count),
+                )
+            })
+            .collect())
+    }
+}
+
+/// use the users stardict directory if it exists else the system one
+pub fn default_stardict_path() -> PathBuf {
+    if let Ok(home) = env::var("HOME") {
+        let user_path = PathBuf::from(home).join(".stardict").join("dic");
+
+        if user_path.exists() {
+            return user_path;
+        }
+    }
+
+    PathBuf::from("/usr/share/stardict/dic")
+}
+
+// a bundle that cant be read is most likely a bad stardict_path
+fn read_error(path: &Path, err: io::Error) -> WordsError {
+    WordsError::Config(format!("cant read {}: {}", path.display(), err))
+}
+
+fn parse_number(
+    ifo_path: &Path,
+    key: &str,
+    val: &str,
+) -> Result<usize, WordsError> {
+    val.parse().map_err(|_| {
+        WordsError::Parse(format!(
+            "{} is not a number in {}",
+            key,
+            ifo_path.display()
+        ))
+    })
+}
+
+fn find_ifo_files(path: &Path, ifo_paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry_path = entry?.path();
+
+        if entry_path.is_dir() {
+            find_ifo_files(&entry_path, ifo_paths)?;
+        } else if entry_path.extension().is_some_and(|ext| ext == "ifo") {
+            ifo_paths.push(entry_path);
+        }
+    }
+
+    Ok(())
+}
+
+/// find a file next to the ifo file with one of the given extensions
+fn find_with_ext(ifo_path: &Path, exts: &[&str]) -> Option<PathBuf> {
+    exts.iter()
+        .map(|ext| ifo_path.with_extension(ext))
+        .find(|path| path.exists())
+}
+
+/// read a file and decompress it if it ends in `.gz` or `.dz`
+fn read_maybe_gz(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+
+    if path
+        .extension()
+        .is_some_and(|ext| ext == "gz" || ext == "dz")
+    {
+        let mut decoded = Vec::new();
+
+        GzDecoder::new(&bytes[..]).read_to_end(&mut decoded)?;
+
+        Ok(decoded)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// find the chunk table in the header of a dictzip file
+///
+/// dictzip is gzip with an `RA` extra field that has the length of the
+/// chunks once inflated and the compressed size of each one, this gives back
+/// the chunk length and where each chunk starts in the file
+fn parse_dictzip_header(header: &[u8]) -> Option<(u64, Vec<u64>)> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let u16_at = |pos: usize| {
+        header
+            .get(pos..pos + 2)
+            .map(|b| u64::from(u16::from_le_bytes([b[0], b[1]])))
+    };
+
+    if header.get(..3) != Some(&[0x1f, 0x8b, 8][..]) {
+        return None;
+    }
+
+    let flags = header[3];
+
+    if flags & FEXTRA == 0 {
+        return None;
+    }
+
+    let extra_len = u16_at(10)? as usize;
+    let extra = header.get(12..12 + extra_len)?;
+
+    let mut chunk_table = None;
+    let mut pos = 0;
+
+    // the extra field is a list of subfields with a two byte id and length
+    while pos + 4 <= extra.len() {
+        let len =
+            usize::from(u16::from_le_bytes([extra[pos + 2], extra[pos + 3]]));
+        let data = extra.get(pos + 4..pos + 4 + len)?;
+
+        if &extra[pos..pos + 2] == b"RA" {
+            chunk_table = Some(data);
+        }
+
+        pos += 4 + len;
+    }
+
+    let table = chunk_table?;
+
+    let table_u16 = |pos: usize| {
+        table
+            .get(pos..pos + 2)
+            .map(|b| u64::from(u16::from_le_bytes([b[0], b[1]])))
+    };
+
+    let chunk_len = table_u16(2)?;
+    let chunk_count = table_u16(4)? as usize;
+
+    if chunk_len == 0 {
+        return None;
+    }
+
+    // the data starts after the optional name, comment and header crc
+    let mut data_start = 12 + extra_len;
+
+    for flag in &[FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            data_start +=
+                header.get(data_start..)?.iter().position(|b| *b == 0)? + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        data_start += 2;
+    }
+
+    let mut chunks = vec![data_start as u64];
+
+    for i in 0..chunk_count {
+        let size = table_u16(6 + i * 2)?;
+
+        chunks.push(chunks[i] + size);
+    }
+
+    Some((chunk_len, chunks))
+}
+
+/// the idx file is a list of null terminated words each followed by a big
+/// endian offset and size
+fn parse_index(
+    bytes: &[u8],
+    offset_bits: usize,
+) -> Result<Vec<IndexEntry>, WordsError> {
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+
+    let mut index = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let word_end = bytes[pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .map(|i| pos + i)
+            .ok_or_else(|| {
+                WordsError::Parse(
+                    "idx file has a word without an end".to_string(),
+                )
+            })?;
+
+        let word = String::from_utf8_lossy(&bytes[pos..word_end]).to_string();
+
+        let nums = bytes
+            .get(word_end + 1..word_end + 1 + offset_len + 4)
+            .ok_or_else(|| {
+                WordsError::Parse(
+                    "idx file ended in the middle of an entry".to_string(),
+                )
+            })?;
+
+        let offset = nums[..offset_len]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+
+        let size = nums[offset_len..]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+
+        index.push(IndexEntry { word, offset, size });
+
+        pos = word_end + 1 + offset_len + 4;
+    }
+
+    Ok(index)
+}
+
+/// compare like glib's `g_ascii_strcasecmp` which is how the index is sorted
+fn ascii_casecmp(a: &str, b: &str) -> Ordering {
+    a.bytes()
+        .map(|c| c.to_ascii_lowercase())
+        .cmp(b.bytes().map(|c| c.to_ascii_lowercase()))
+}
+
+/// split the data for an entry in to its typed fields
+///
+/// with a `sametypesequence` the types are left out of the data and the last
+/// field has no terminator or size, with out one every field starts with its
+/// type, lower case types are null terminated and upper case types start with
+/// a big endian size
+fn parse_data(
+    data: &[u8],
+    same_type_sequence: Option<&str>,
+) -> Vec<(char, String)> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    let types: Vec<char> = same_type_sequence
+        .map(|seq| seq.chars().collect())
+        .unwrap_or_default();
+
+    let mut type_index = 0;
+
+    while pos < data.len() {
+        let field_type = if types.is_empty() {
+            let field_type = data[pos] as char;
+
+            pos += 1;
+
+            field_type
+        } else if type_index < types.len() {
+            type_index += 1;
+
+            types[type_index - 1]
+        } else {
+            break;
+        };
+
+        let is_last = !types.is_empty() && type_index == types.len();
+
+        let (field, next) = if is_last {
+            (&data[pos..], data.len())
+        } else if field_type.is_ascii_lowercase() {
+            let end = data[pos..]
+                .iter()
+                .position(|b| *b == 0)
+                .map_or(data.len(), |i| pos + i);
+
+            (&data[pos..end], end + 1)
+        } else {
+            let size = data.get(pos..pos + 4).map_or(0, |s| {
+                s.iter().fold(0, |acc, b| (acc << 8) | *b as usize)
+            });
+
+            let end = (pos + 4 + size).min(data.len());
+
+            (data.get(pos + 4..end).unwrap_or(&[]), end)
+        };
+
+        fields.push((field_type, String::from_utf8_lossy(field).to_string()));
+
+        pos = next;
+    }
+
+    fields
+}
+
+/// turn html, pango or xdxf markup in to plain text
+fn strip_markup(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '<' {
+            let tag: String =
+                chars.by_ref().take_while(|c| *c != '>').collect();
+
+            let tag = tag.trim().to_lowercase();
+
+            if tag.starts_with("br") || tag == "/p" || tag == "/div" {
+                output.push('\n');
+            }
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compress, Compression, Crc, FlushCompress};
+
+    use crate::test_utils::TempSetup;
+
+    // dictzip the data with tiny chunks so entries span more then one
+    fn dictzip(data: &[u8], chunk_len: usize) -> Vec<u8> {
+        let mut compressor = Compress::new(Compression::default(), false);
+        let mut body = Vec::new();
+        let mut sizes = Vec::new();
+
+        let chunks: Vec<&[u8]> = data.chunks(chunk_len).collect();
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let flush = if i + 1 == chunks.len() {
+                FlushCompress::Finish
+            } else {
+                FlushCompress::Full
+            };
+
+            let before = body.len();
+
+            body.reserve(chunk.len() * 2 + 64);
+            compressor.compress_vec(chunk, &mut body, flush).unwrap();
+
+            sizes.push((body.len() - before) as u16);
+        }
+
+        let mut ra: Vec<u8> = Vec::new();
+
+        ra.extend(&1u16.to_le_bytes());
+        ra.extend(&(chunk_len as u16).to_le_bytes());
+        ra.extend(&(sizes.len() as u16).to_le_bytes());
+
+        for size in sizes {
+            ra.extend(&size.to_le_bytes());
+        }
+
+        let mut file = vec![0x1f, 0x8b, 8, 0x04 | 0x08, 0, 0, 0, 0, 0, 3];
+
+        file.extend(&(ra.len() as u16 + 4).to_le_bytes());
+        file.extend(b"RA");
+        file.extend(&(ra.len() as u16).to_le_bytes());
+        file.extend(ra);
+        file.extend(b"fake.dict\0");
+        file.extend(body);
+
+        let mut crc = Crc::new();
+
+        crc.update(data);
+
+        file.extend(&crc.sum().to_le_bytes());
+        file.extend(&(data.len() as u32).to_le_bytes());
+
+        file
+    }
+
+    // write a bundle with the given words and data, the words need to be in
+    // the StarDict sort order already
+    fn fake_bundle(
+        root: &Path,
+        name: &str,
+        same_type_sequence: Option<&str>,
+        words: &[(&str, &[u8])],
+        compress: bool,
+    ) {
+        let mut idx = Vec::new();
+        let mut dict = Vec::new();
+
+        for (word, data) in words {
+            idx.extend(word.as_bytes());
+            idx.push(0);
+            idx.extend(&(dict.len() as u32).to_be_bytes());
+            idx.extend(&(data.len() as u32).to_be_bytes());
+
+            dict.extend(*data);
+        }
+
+        let mut ifo = format!(
+            "StarDict's dict ifo file\nversion=2.4.2\nbookname={}\n\
+             wordcount={}\nidxfilesize={}\n",
+            name,
+            words.len(),
+            idx.len()
+        );
+
+        if let Some(seq) = same_type_sequence {
+            ifo.push_str(&format!("sametypesequence={}\n", seq));
+        }
+
+        fs::write(root.join(format!("{}.ifo", name)), ifo).unwrap();
+        fs::write(root.join(format!("{}.idx", name)), idx).unwrap();
+
+        if compress {
+            fs::write(
+                root.join(format!("{}.dict.dz", name)),
+                dictzip(&dict, 8),
+            )
+            .unwrap();
+        } else {
+            fs::write(root.join(format!("{}.dict", name)), dict).unwrap();
+        }
+    }
+
+    fn fake_stardict(root: &Path) {
+        fake_bundle(
+            root,
+            "english",
+            Some("tm"),
+            &[
+                ("apple", b"/ap-ple/\0a round fruit\nthat grows on trees"),
+                ("Flagrant", b"/fla-grant/\0conspicuously offensive"),
+                ("zebra", b"/zeb-ra/\0a striped horse"),
+            ],
+            false,
+        );
+
+        let sub_path = root.join("french");
+
+        fs::create_dir_all(&sub_path).unwrap();
+
+        fake_bundle(
+            &sub_path,
+            "english-french",
+            None,
+            &[
+                ("flagrant", b"h<b>flagrant</b><br>&lt;adj&gt;\0"),
+                ("flagrant", b"mflagrante\0"),
+            ],
+            true,
+        );
+    }
+
+    #[test]
+    fn test_parse_data() {
+        let fields = parse_data(b"/fo/\0some text", Some("tm"));
+
+        assert_eq!(
+            fields,
+            vec![('t', "/fo/".to_string()), ('m', "some text".to_string())],
+            "did not parse a same type sequence"
+        );
+
+        let fields = parse_data(b"mtext\0W\0\0\0\x02abhhtml\0", None);
+
+        assert_eq!(
+            fields,
+            vec![
+                ('m', "text".to_string()),
+                ('W', "ab".to_string()),
+                ('h', "html".to_string())
+            ],
+            "did not parse typed fields"
+        );
+    }
+
+    #[test]
+    fn test_strip_markup() {
+        assert_eq!(
+            strip_markup("<b>word</b><br/>&lt;n&gt; &amp; more"),
+            "word\n<n> & more",
+            "did not strip the markup"
+        );
+    }
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        let bundle = Bundle::open(&root_path.join("english.ifo")).unwrap();
+
+        for word in &["apple", "FLAGRANT", "flagrant", "zebra"] {
+            assert_eq!(bundle.lookup(word).len(), 1, "did not find {}", word);
+        }
+
+        for word in &["a", "banana", "zzz"] {
+            assert!(bundle.lookup(word).is_empty(), "found {}", word);
+        }
+    }
+
+    #[test]
+    fn test_get_definition() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        let stardict = StarDict::new(root_path, "*");
+
+        let word_data = stardict.get_definition("flagrant").unwrap().remove(0);
+
+        assert_eq!(word_data.word, "Flagrant", "got the wrong headword");
+
+        assert_eq!(word_data.phonetics[0].text, "/fla-grant/");
+
+        let meanings: Vec<(&str, Vec<&str>)> = word_data
+            .meanings
+            .iter()
+            .map(|m| {
+                (
+                    m.partOfSpeech.as_str(),
+                    m.definitions
+                        .iter()
+                        .map(|d| d.definition.as_str())
+                        .collect(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            meanings,
+            vec![
+                ("english", vec!["conspicuously offensive"]),
+                ("english-french", vec!["flagrant", "<adj>", "flagrante"]),
+            ],
+            "did not read every bundle"
+        );
+    }
+
+    #[test]
+    fn test_get_definition_one_database() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        let stardict = StarDict::new(root_path.clone(), "english-french");
+
+        let word_data = stardict.get_definition("flagrant").unwrap().remove(0);
+
+        assert_eq!(word_data.meanings.len(), 1, "looked in other databases");
+
+        let stardict = StarDict::new(root_path, "!");
+
+        let word_data = stardict.get_definition("apple").unwrap().remove(0);
+
+        assert_eq!(
+            word_data.meanings[0].definitions.len(),
+            2,
+            "did not split the definition lines"
+        );
+
+        assert!(
+            stardict.get_definition("banana").is_err(),
+            "found a word that is not in any bundle"
+        );
+    }
+
+    #[test]
+    fn test_read_dictzip() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let data = b"the quick brown fox jumps over the lazy dog";
+
+        let dict_path = root_path.join("fake.dict.dz");
+
+        fs::write(&dict_path, dictzip(data, 8)).unwrap();
+
+        let dict = DictFile::open(dict_path.clone()).unwrap();
+
+        assert!(
+            matches!(dict, DictFile::DictZip { .. }),
+            "did not find the chunk table"
+        );
+
+        // one chunk, across chunks and the end of the data
+        for (offset, size) in &[(0, 3), (4, 5), (6, 20), (40, 3)] {
+            assert_eq!(
+                dict.read(*offset as u64, *size).unwrap(),
+                &data[*offset..offset + size],
+                "read the wrong bytes at {}",
+                offset
+            );
+        }
+
+        assert!(dict.read(40, 10).is_err(), "read past the end");
+
+        // it is a real gzip file as well
+        let mut inflated = Vec::new();
+
+        GzDecoder::new(&fs::read(&dict_path).unwrap()[..])
+            .read_to_end(&mut inflated)
+            .unwrap();
+
+        assert_eq!(inflated, &data[..]);
+    }
+
+    #[test]
+    fn test_read_gzip_without_chunks() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+
+        encoder.write_all(b"plain gzip data").unwrap();
+
+        let dict_path = root_path.join("fake.dict.dz");
+
+        fs::write(&dict_path, encoder.finish().unwrap()).unwrap();
+
+        let dict = DictFile::open(dict_path).unwrap();
+
+        assert_eq!(dict.read(6, 4).unwrap(), b"gzip");
+    }
+
+    #[test]
+    fn test_bad_bundles_are_skipped() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        fs::write(root_path.join("broken.ifo"), "not an ifo file").unwrap();
+
+        let stardict = StarDict::new(root_path, "*");
+
+        let word_data = stardict.get_definition("apple").unwrap().remove(0);
+
+        assert_eq!(word_data.meanings[0].partOfSpeech, "english");
+        assert_eq!(stardict.databases().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_bundles_are_indexed_once() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        let stardict = StarDict::new(root_path.clone(), "english");
+
+        assert!(stardict.get_definition("apple").is_ok());
+
+        // the index is kept so it is not read again
+        fs::remove_file(root_path.join("english.idx")).unwrap();
+
+        assert!(
+            stardict.get_definition("zebra").is_ok(),
+            "read the index again"
+        );
+    }
+
+    #[test]
+    fn test_databases() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_stardict(&root_path);
+
+        let stardict = StarDict::new(root_path, "*");
+
+        let mut databases = stardict.databases().unwrap();
+
+        databases.sort();
+
+        assert_eq!(
+            databases,
+            vec![
+                ("english".to_string(), "3 words".to_string()),
+                ("english-french".to_string(), "2 words".to_string()),
+            ],
+            "did not list the bundles"
+        );
+    }
+}
@@ -1,19 +1,33 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
 
 use tempfile::{tempdir, TempDir};
 
 use crate::dict_api::*;
 
-const MONSTER: &'static str = r#"{"word":"monster","phonetics":[{"text":"/ˈmɑnstər/","audio":"https://lex-audio.useremarkable.com/mp3/monster_us_1.mp3"}],"meanings":[{"partOfSpeech":"noun","definitions":[{"definition":"An imaginary creature that is typically large, ugly, and frightening.","example":"She was made into a horrid, ugly monster.","synonyms":["fabulous creature","mythical creature"]}]},{"partOfSpeech":"transitive verb","definitions":[{"definition":"Criticize or reprimand severely.","example":null,"synonyms":["criticize","censure","condemn","castigate","chastise","lambast","pillory","savage","find fault with","fulminate against","abuse"]}]}]}"#;
+// tests that change environment variables run one at a time
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+const MONSTER: &str = r#"{"word":"monster","phonetics":[{"text":"/ˈmɑnstər/","audio":"https://lex-audio.useremarkable.com/mp3/monster_us_1.mp3"}],"meanings":[{"partOfSpeech":"noun","definitions":[{"definition":"An imaginary creature that is typically large, ugly, and frightening.","example":"She was made into a horrid, ugly monster.","synonyms":["fabulous creature","mythical creature"]}]},{"partOfSpeech":"transitive verb","definitions":[{"definition":"Criticize or reprimand severely.","example":null,"synonyms":["criticize","censure","condemn","castigate","chastise","lambast","pillory","savage","find fault with","fulminate against","abuse"]}]}]}"#;
+
+/// hold this while changing environment variables
+pub fn env_lock() -> MutexGuard<'static, ()> {
+    // a failed test should not fail every test after it
+    ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
 
 #[derive(Default)]
 pub struct TempSetup {
     pub root: PathBuf,
     pub temp: Option<TempDir>,
+    lock: Option<MutexGuard<'static, ()>>,
 }
 
 impl TempSetup {
     pub fn setup(&mut self) -> PathBuf {
+        self.lock = Some(env_lock());
         self.temp = Some(tempdir().unwrap());
         self.root = self.temp.as_ref().unwrap().path().to_owned();
 
@@ -26,6 +40,8 @@ impl Drop for TempSetup {
         if let Some(temp) = self.temp.take() {
             temp.close().expect("cant close file");
         }
+
+        self.lock.take();
     }
 }
 
@@ -56,6 +72,7 @@ pub fn fake_word_data() -> WordData {
         definition: "test definition".to_string(),
         example: Some("test example text".to_string()),
         synonyms,
+        antonyms: None,
     }];
 
     let meanings = vec![Meaning {
@@ -67,5 +84,6 @@ pub fn fake_word_data() -> WordData {
         word: "test".to_string(),
         meanings,
         phonetics,
+        etymology: None,
     }
 }
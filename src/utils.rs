@@ -1,39 +1,45 @@
 use std::process::Command;
-use std::{env, error::Error, path::PathBuf};
+use std::{env, path::PathBuf};
+
+use crate::error::WordsError;
 
 /// get the terminal columns from the tput command
-pub fn get_tty_cols() -> usize {
+pub fn get_tty_cols() -> Result<usize, WordsError> {
     use std::str;
 
-    let cmd = Command::new("tput")
-        .arg("cols")
-        .output()
-        .expect("failed to run tput proses");
-
-    if cmd.status.success() {
-        str::from_utf8(&cmd.stdout)
-            .expect("cant cast output as str ")
-            .trim()
-            .parse::<usize>()
-            .expect("cant convert output to a number")
-    } else {
-        panic!("tput failed to run");
+    let cmd = Command::new("tput").arg("cols").output().map_err(|e| {
+        WordsError::Config(format!("failed to run tput proses: {}", e))
+    })?;
+
+    if !cmd.status.success() {
+        return Err(WordsError::Config("tput failed to run".to_string()));
     }
+
+    str::from_utf8(&cmd.stdout)
+        .ok()
+        .and_then(|cols| cols.trim().parse::<usize>().ok())
+        .ok_or_else(|| {
+            WordsError::Config(
+                "cant convert tput output to a number".to_string(),
+            )
+        })
 }
 
 // try and use either XDG_DATA_HOME or HOME else return an error
-pub fn get_data_path() -> Result<PathBuf, Box<dyn Error>> {
+pub fn get_data_path() -> Result<PathBuf, WordsError> {
     if let Ok(data_home) = env::var("XDG_DATA_HOME") {
         if data_home.is_empty() {
-            return Err(Box::from(
-                "XDG_DATA_HOME not set correctly and is empty",
+            return Err(WordsError::Config(
+                "XDG_DATA_HOME not set correctly and is empty".to_string(),
             ));
         }
 
         Ok(PathBuf::from(data_home).join("words_cli"))
     } else if let Ok(val) = env::var("HOME") {
         if val.is_empty() {
-            return Err(Box::from("HOME not set correctly and is empty"));
+            return Err(WordsError::Config(
+                "HOME not set correctly and is empty".to_string(),
+            ));
         }
 
         Ok(PathBuf::from(val)
@@ -41,40 +47,128 @@ pub fn get_data_path() -> Result<PathBuf, Box<dyn Error>> {
             .join("share")
             .join("words_cli"))
     } else {
-        Err(Box::from(
-            "environment variables HOME and XDG_DATA_HOME are not set",
+        Err(WordsError::Config(
+            "environment variables HOME and XDG_DATA_HOME are not set"
+                .to_string(),
         ))
     }
 }
 
-pub fn get_user_config_path() -> Result<PathBuf, Box<dyn Error>> {
+pub fn get_user_config_path() -> Result<PathBuf, WordsError> {
     if let Ok(config_home) = env::var("XDG_CONFIG_HOME") {
         if config_home.is_empty() {
-            return Err(Box::from(
-                "XDG_CONFIG_HOME not set correctly and is empty",
+            return Err(WordsError::Config(
+                "XDG_CONFIG_HOME not set correctly and is empty".to_string(),
             ));
         }
 
         Ok(PathBuf::from(config_home).join("words_cli"))
     } else if let Ok(val) = env::var("HOME") {
         if val.is_empty() {
-            return Err(Box::from("HOME not set correctly and is empty"));
+            return Err(WordsError::Config(
+                "HOME not set correctly and is empty".to_string(),
+            ));
         }
 
         Ok(PathBuf::from(val).join(".config").join("words_cli"))
     } else {
-        Err(Box::from(
-            "environment variables HOME and XDG_CONFIG_HOME are not set",
+        Err(WordsError::Config(
+            "environment variables HOME and XDG_CONFIG_HOME are not set"
+                .to_string(),
         ))
     }
 }
 
+/// days since the epoch for a date, or nothing if it is before 1970
+pub fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    // Howard Hinnant's days_from_civil
+    let (year, month) = if month <= 2 {
+        (year.checked_sub(1)?, month + 9)
+    } else {
+        (year, month - 3)
+    };
+
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    (era * 146_097 + day_of_era).checked_sub(719_468)
+}
+
+/// read a `YYYY-MM-DD` date as seconds since the epoch at the start of that
+/// day in UTC
+pub fn parse_date(date: &str) -> Option<i64> {
+    let parts: Vec<u64> = date
+        .trim()
+        .split('-')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match parts.as_slice() {
+        [year, month, day]
+            if (1..=12).contains(month) && (1..=31).contains(day) =>
+        {
+            Some(days_from_civil(*year, *month, *day)? as i64 * 24 * 60 * 60)
+        }
+        _ => None,
+    }
+}
+
+/// turn seconds since the epoch in to a `YYYY-MM-DD` date
+pub fn format_date(unix_time: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let days = unix_time.div_euclid(24 * 60 * 60) + 719_468;
+
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// turn seconds since the epoch in to a `YYYY-MM-DD HH:MM` time in UTC
+pub fn format_time(unix_time: i64) -> String {
+    let seconds = unix_time.rem_euclid(24 * 60 * 60);
+
+    format!(
+        "{} {:02}:{:02}",
+        format_date(unix_time),
+        seconds / (60 * 60),
+        seconds % (60 * 60) / 60
+    )
+}
+
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod test {
     use super::*;
 
+    use crate::test_utils::env_lock;
+
     #[test]
     fn test_get_cache_path() {
+        let _lock = env_lock();
+
         env::set_var("XDG_DATA_HOME", "test");
 
         let path = get_data_path().unwrap();
@@ -88,6 +182,8 @@ mod test {
 
     #[test]
     fn test_get_cache_path_no_var_data() {
+        let _lock = env_lock();
+
         env::set_var("XDG_DATA_HOME", "");
 
         if let Err(err) = get_data_path() {
@@ -100,4 +196,31 @@ mod test {
             assert!(false, "some how got data in env var")
         }
     }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_445_412_480), "2015-10-21");
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00");
+        assert_eq!(format_time(1_445_412_480), "2015-10-21 07:28");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-02-29"), Some(951_782_400));
+        assert_eq!(
+            parse_date("2015-10-21").map(format_date).unwrap(),
+            "2015-10-21"
+        );
+
+        assert_eq!(parse_date("2015-13-01"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
 }
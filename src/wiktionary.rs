@@ -0,0 +1,1266 @@
+//! import Wiktionary dumps in to the cache
+//!
+//! this reads either a Wiktextract JSONL dump, like the ones from
+//! https://kaikki.org, or a raw Wiktionary XML dump and turns every word in the
+//! given language in to WordData, both formats are read a line at a time so
+//! the whole dump never has to fit in memory
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+
+use crate::{
+    cache::{cache_key, Cache, CacheScope, WIKTIONARY_SOURCE},
+    dict_api::{Definition, Meaning, Phonetic, WordData},
+    error::WordsError,
+};
+
+/// the kinds of dumps that can be imported
+#[derive(Debug, PartialEq)]
+pub enum DumpFormat {
+    Jsonl,
+    Xml,
+}
+
+impl DumpFormat {
+    /// guess the format from the file name, `-` and anything unknown is
+    /// treated as JSONL
+    fn from_path(path: &str) -> Self {
+        let path = path.trim_end_matches(".gz");
+
+        if path.ends_with(".xml") {
+            DumpFormat::Xml
+        } else {
+            DumpFormat::Jsonl
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WiktextractLink {
+    word: String,
+}
+
+#[derive(Deserialize)]
+struct WiktextractExample {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WiktextractSound {
+    ipa: Option<String>,
+    mp3_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WiktextractSense {
+    #[serde(default)]
+    glosses: Vec<String>,
+    #[serde(default)]
+    examples: Vec<WiktextractExample>,
+    #[serde(default)]
+    synonyms: Vec<WiktextractLink>,
+    #[serde(default)]
+    antonyms: Vec<WiktextractLink>,
+}
+
+#[derive(Deserialize)]
+struct WiktextractEntry {
+    word: String,
+    pos: Option<String>,
+    lang_code: Option<String>,
+    etymology_text: Option<String>,
+    /// only set when a word has more then one etymology
+    etymology_number: Option<u32>,
+    #[serde(default)]
+    senses: Vec<WiktextractSense>,
+    #[serde(default)]
+    sounds: Vec<WiktextractSound>,
+    #[serde(default)]
+    synonyms: Vec<WiktextractLink>,
+    #[serde(default)]
+    antonyms: Vec<WiktextractLink>,
+}
+
+/// how an import went
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportCount {
+    /// the words saved that were not cached before
+    pub words: usize,
+    /// the words that were already cached and got replaced
+    pub replaced: usize,
+    /// the lines that could not be read and were left out
+    pub skipped: usize,
+}
+
+/// import a dump file, or stdin if the path is `-`, in to the cache
+pub fn import(
+    path: &str,
+    format: Option<&str>,
+    lang: &str,
+) -> Result<ImportCount, WordsError> {
+    let format = match format {
+        Some("jsonl") => DumpFormat::Jsonl,
+        Some("xml") => DumpFormat::Xml,
+        Some(format) => {
+            return Err(WordsError::BadInput(format!(
+                "unknown dump format: {}",
+                format
+            )))
+        }
+        None => DumpFormat::from_path(path),
+    };
+
+    let input: Box<dyn Read> = if path == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(path).map_err(|e| {
+            WordsError::BadInput(format!("cant open {}: {}", path, e))
+        })?)
+    };
+
+    let input: Box<dyn Read> =
+        if Path::new(path).extension().is_some_and(|ext| ext == "gz") {
+            Box::new(GzDecoder::new(input))
+        } else {
+            input
+        };
+
+    // wiktionary is what dictionaryapi is made from so the words are used in
+    // its place
+    let cache = Cache::open()?.with_scope(CacheScope {
+        lang: lang.to_string(),
+        ..CacheScope::default()
+    });
+
+    // save the whole dump in one transaction, one per word is very slow
+    cache.begin()?;
+
+    let mut saved = HashSet::new();
+    let mut replaced = 0;
+
+    let mut imported =
+        import_dump(BufReader::new(input), &format, lang, |entries| {
+            save_import(&cache, &mut saved, &mut replaced, entries)
+        })?;
+
+    cache.commit()?;
+
+    // headwords that only differ by case are one word in the cache
+    imported.words = saved.len() - replaced;
+    imported.replaced = replaced;
+
+    Ok(imported)
+}
+
+/// cache the entries for a word, `saved` has the keys already saved by this
+/// import and when the word folds to one of them, like "Bass" and "bass", its
+/// entries are added to the ones saved before instead of replacing them
+///
+/// a word that was cached before the import is replaced and counted in
+/// `replaced`
+fn save_import(
+    cache: &Cache,
+    saved: &mut HashSet<String>,
+    replaced: &mut usize,
+    mut entries: Vec<WordData>,
+) -> Result<(), WordsError> {
+    // every group of entries has at least one entry
+    let word = entries[0].word.to_owned();
+
+    if saved.insert(cache_key(&word)) {
+        if cache.contains(&word)? {
+            *replaced += 1;
+        }
+    } else if let Some(cached) = cache.peek(&word)? {
+        entries.splice(0..0, cached.entries);
+    }
+
+    cache.cache_definition(&word, &entries, WIKTIONARY_SOURCE)
+}
+
+/// read a dump and give the entries for every word in the language to
+/// `save`, a word gets an entry for each of its etymologies
+pub fn import_dump<R, F>(
+    reader: R,
+    format: &DumpFormat,
+    lang: &str,
+    save: F,
+) -> Result<ImportCount, WordsError>
+where
+    R: BufRead,
+    F: FnMut(Vec<WordData>) -> Result<(), WordsError>,
+{
+    match format {
+        DumpFormat::Jsonl => import_jsonl(reader, lang, save),
+        DumpFormat::Xml => import_xml(reader, &language_name(lang), save),
+    }
+}
+
+/// Wiktextract has a line for each part of speech of a word and they come one
+/// after the other so merge lines until the word changes, words that only
+/// differ by case are kept together since they are one word in the cache
+///
+/// a line that is not valid JSON is skipped and counted so one bad line does
+/// not throw away the rest of a big dump
+fn import_jsonl<R, F>(
+    reader: R,
+    lang: &str,
+    mut save: F,
+) -> Result<ImportCount, WordsError>
+where
+    R: BufRead,
+    F: FnMut(Vec<WordData>) -> Result<(), WordsError>,
+{
+    let mut count = ImportCount::default();
+
+    // the entries for the word being read and the headword and etymology
+    // number of each
+    let mut current: Vec<WordData> = Vec::new();
+    let mut etymologies: Vec<(String, Option<u32>)> = Vec::new();
+
+    for line in reader.lines() {
+        let line = line.map_err(read_error)?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry: WiktextractEntry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(_) => {
+                count.skipped += 1;
+
+                continue;
+            }
+        };
+
+        if entry.lang_code.as_deref() != Some(lang) {
+            continue;
+        }
+
+        if current
+            .first()
+            .is_some_and(|word| cache_key(&word.word) != cache_key(&entry.word))
+        {
+            count.words += save_entries(&mut save, &mut current)?;
+
+            etymologies.clear();
+        }
+
+        // a line with a different etymology or headword is a different entry
+        // for the same word, like the fish and the sound for "bass" or the
+        // surname "Bass"
+        let index = etymologies.iter().position(|(word, number)| {
+            *word == entry.word && *number == entry.etymology_number
+        });
+
+        let index = match index {
+            Some(index) => index,
+            None => {
+                current.push(new_entry(&entry.word));
+                etymologies
+                    .push((entry.word.to_owned(), entry.etymology_number));
+
+                current.len() - 1
+            }
+        };
+
+        merge_entry(&mut current[index], entry);
+    }
+
+    count.words += save_entries(&mut save, &mut current)?;
+
+    Ok(count)
+}
+
+fn new_entry(word: &str) -> WordData {
+    WordData {
+        word: word.to_string(),
+        phonetics: Vec::new(),
+        meanings: Vec::new(),
+        etymology: None,
+    }
+}
+
+/// give the entries that have meanings to `save`, returns 1 if it saved
+/// anything so it can be added to the count
+fn save_entries<F>(
+    save: &mut F,
+    entries: &mut Vec<WordData>,
+) -> Result<usize, WordsError>
+where
+    F: FnMut(Vec<WordData>) -> Result<(), WordsError>,
+{
+    let entries: Vec<WordData> = entries
+        .drain(..)
+        .filter(|word_data| !word_data.meanings.is_empty())
+        .collect();
+
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    save(entries)?;
+
+    Ok(1)
+}
+
+fn merge_entry(word_data: &mut WordData, entry: WiktextractEntry) {
+    for sound in entry.sounds {
+        if let Some(ipa) = sound.ipa {
+            if !word_data.phonetics.iter().any(|p| p.text == ipa) {
+                word_data.phonetics.push(Phonetic {
+                    text: ipa,
+                    audio: String::new(),
+                });
+            }
+        } else if let Some(mp3_url) = sound.mp3_url {
+            if let Some(phonetic) =
+                word_data.phonetics.iter_mut().find(|p| p.audio.is_empty())
+            {
+                phonetic.audio = mp3_url;
+            }
+        }
+    }
+
+    if word_data.etymology.is_none() {
+        word_data.etymology = entry.etymology_text.filter(|e| !e.is_empty());
+    }
+
+    let mut definitions: Vec<Definition> = entry
+        .senses
+        .into_iter()
+        .filter_map(|sense| {
+            // sub senses have the parent gloss first so the last one is the
+            // most specific
+            let definition = sense.glosses.into_iter().last()?;
+
+            Some(Definition {
+                definition,
+                example: sense
+                    .examples
+                    .into_iter()
+                    .find_map(|example| example.text),
+                synonyms: links_to_words(sense.synonyms),
+                antonyms: links_to_words(sense.antonyms),
+            })
+        })
+        .collect();
+
+    if definitions.is_empty() {
+        return;
+    }
+
+    // synonyms for the whole word go on the first definition
+    if let Some(first) = definitions.first_mut() {
+        if first.synonyms.is_none() {
+            first.synonyms = links_to_words(entry.synonyms);
+        }
+
+        if first.antonyms.is_none() {
+            first.antonyms = links_to_words(entry.antonyms);
+        }
+    }
+
+    let part_of_speech = match entry.pos.as_deref() {
+        Some("adj") => "adjective".to_string(),
+        Some("adv") => "adverb".to_string(),
+        Some("name") => "proper noun".to_string(),
+        Some("prep") => "preposition".to_string(),
+        Some("conj") => "conjunction".to_string(),
+        Some("intj") => "interjection".to_string(),
+        Some("det") => "determiner".to_string(),
+        Some("num") => "numeral".to_string(),
+        Some(pos) => pos.to_string(),
+        None => String::new(),
+    };
+
+    word_data.meanings.push(Meaning {
+        partOfSpeech: part_of_speech,
+        definitions,
+    });
+}
+
+fn links_to_words(links: Vec<WiktextractLink>) -> Option<Vec<String>> {
+    if links.is_empty() {
+        None
+    } else {
+        Some(links.into_iter().map(|link| link.word).collect())
+    }
+}
+
+/// the XML dump is one `<page>` per title with the wikitext in `<text>`, this
+/// only looks at the main namespace
+fn import_xml<R, F>(
+    reader: R,
+    lang_name: &str,
+    mut save: F,
+) -> Result<ImportCount, WordsError>
+where
+    R: BufRead,
+    F: FnMut(Vec<WordData>) -> Result<(), WordsError>,
+{
+    let mut count = ImportCount::default();
+
+    let mut title = String::new();
+    let mut namespace = String::new();
+    let mut text = String::new();
+    let mut in_text = false;
+
+    for line in reader.lines() {
+        let line = line.map_err(read_error)?;
+
+        let mut rest = line.as_str();
+
+        if !in_text {
+            let trimmed = line.trim();
+
+            if let Some(value) = tag_value(trimmed, "title") {
+                title = unescape_xml(value);
+                continue;
+            }
+
+            if let Some(value) = tag_value(trimmed, "ns") {
+                namespace = value.to_string();
+                continue;
+            }
+
+            match trimmed.find("<text") {
+                Some(start) => {
+                    let open = &trimmed[start..];
+
+                    // an empty page
+                    if open.ends_with("/>") {
+                        continue;
+                    }
+
+                    rest = match open.find('>') {
+                        Some(end) => &open[end + 1..],
+                        None => continue,
+                    };
+
+                    in_text = true;
+
+                    text.clear();
+                }
+                None => continue,
+            }
+        }
+
+        if let Some(end) = rest.find("</text>") {
+            text.push_str(&rest[..end]);
+
+            in_text = false;
+
+            if namespace == "0" {
+                let mut entries =
+                    parse_wikitext(&title, &unescape_xml(&text), lang_name);
+
+                count.words += save_entries(&mut save, &mut entries)?;
+            }
+        } else {
+            text.push_str(rest);
+            text.push('\n');
+        }
+    }
+
+    Ok(count)
+}
+
+// the dump is given by the user so reading it is a problem with the input
+fn read_error(err: io::Error) -> WordsError {
+    WordsError::BadInput(format!("cant read the dump: {}", err))
+}
+
+fn tag_value<'a>(line: &'a str, tag: &str) -> Option<&'a str> {
+    line.strip_prefix(&format!("<{}>", tag))?
+        .strip_suffix(&format!("</{}>", tag))
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#039;", "'")
+        .replace("&amp;", "&")
+}
+
+/// wiktionary headings use the language name not the code
+fn language_name(lang: &str) -> String {
+    match lang {
+        "en" => "English",
+        "fr" => "French",
+        "de" => "German",
+        "es" => "Spanish",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "nl" => "Dutch",
+        "sv" => "Swedish",
+        "da" => "Danish",
+        "no" => "Norwegian",
+        "fi" => "Finnish",
+        "pl" => "Polish",
+        "ru" => "Russian",
+        "la" => "Latin",
+        "el" => "Greek",
+        "ja" => "Japanese",
+        "zh" => "Chinese",
+        "ko" => "Korean",
+        // let people give the name themselves
+        name => name,
+    }
+    .to_string()
+}
+
+/// the parts of a language section we care about
+enum Section {
+    Etymology,
+    Pronunciation,
+    PartOfSpeech,
+    Synonyms,
+    Antonyms,
+    Other,
+}
+
+const PARTS_OF_SPEECH: [&str; 18] = [
+    "noun",
+    "proper noun",
+    "verb",
+    "adjective",
+    "adverb",
+    "pronoun",
+    "preposition",
+    "conjunction",
+    "interjection",
+    "determiner",
+    "article",
+    "numeral",
+    "particle",
+    "phrase",
+    "prefix",
+    "suffix",
+    "idiom",
+    "proverb",
+];
+
+/// turn the section for a language on a page in to WordData, pages with
+/// numbered etymologies get an entry for each one
+fn parse_wikitext(title: &str, text: &str, lang_name: &str) -> Vec<WordData> {
+    let mut entries = Vec::new();
+
+    let mut word_data = new_entry(title);
+
+    let mut in_lang = false;
+    let mut section = Section::Other;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+
+        if let Some((level, heading)) = parse_heading(line) {
+            if level == 2 {
+                in_lang = heading == lang_name;
+                continue;
+            }
+
+            if !in_lang {
+                continue;
+            }
+
+            let heading = heading.to_lowercase();
+
+            // `Etymology 2` and on start a new entry
+            if heading.starts_with("etymology ")
+                && (word_data.etymology.is_some()
+                    || !word_data.meanings.is_empty())
+            {
+                entries.push(word_data);
+
+                word_data = new_entry(title);
+            }
+
+            section = if heading.starts_with("etymology") {
+                Section::Etymology
+            } else if heading == "pronunciation" {
+                Section::Pronunciation
+            } else if heading == "synonyms" {
+                Section::Synonyms
+            } else if heading == "antonyms" {
+                Section::Antonyms
+            } else if PARTS_OF_SPEECH.contains(&heading.as_str()) {
+                word_data.meanings.push(Meaning {
+                    partOfSpeech: heading,
+                    definitions: Vec::new(),
+                });
+
+                Section::PartOfSpeech
+            } else {
+                Section::Other
+            };
+
+            continue;
+        }
+
+        if !in_lang || line.is_empty() {
+            continue;
+        }
+
+        match section {
+            Section::Etymology => {
+                if word_data.etymology.is_none() {
+                    let etymology = clean_wikitext(line);
+
+                    if !etymology.is_empty() {
+                        word_data.etymology = Some(etymology);
+                    }
+                }
+            }
+            Section::Pronunciation => {
+                for ipa in template_words(line, &["IPA"]) {
+                    if !word_data.phonetics.iter().any(|p| p.text == ipa) {
+                        word_data.phonetics.push(Phonetic {
+                            text: ipa,
+                            audio: String::new(),
+                        });
+                    }
+                }
+            }
+            Section::PartOfSpeech => {
+                let definitions = match word_data.meanings.last_mut() {
+                    Some(meaning) => &mut meaning.definitions,
+                    None => continue,
+                };
+
+                if line.starts_with("#*") || line.starts_with("#:*") {
+                    // quotations are too long to show
+                    continue;
+                } else if line.starts_with("#:") {
+                    let definition = match definitions.last_mut() {
+                        Some(definition) => definition,
+                        None => continue,
+                    };
+
+                    let synonyms = template_words(line, &["syn", "synonyms"]);
+                    let antonyms = template_words(line, &["ant", "antonyms"]);
+
+                    if !synonyms.is_empty() {
+                        definition
+                            .synonyms
+                            .get_or_insert_with(Vec::new)
+                            .extend(synonyms);
+                    } else if !antonyms.is_empty() {
+                        definition
+                            .antonyms
+                            .get_or_insert_with(Vec::new)
+                            .extend(antonyms);
+                    } else if definition.example.is_none() {
+                        let example = clean_wikitext(
+                            line.trim_start_matches(&['#', ':'][..]),
+                        );
+
+                        if !example.is_empty() {
+                            definition.example = Some(example);
+                        }
+                    }
+                } else if line.starts_with('#') {
+                    let definition =
+                        clean_wikitext(line.trim_start_matches('#'));
+
+                    if !definition.is_empty() {
+                        definitions.push(Definition {
+                            definition,
+                            example: None,
+                            synonyms: None,
+                            antonyms: None,
+                        });
+                    }
+                }
+            }
+            Section::Synonyms | Section::Antonyms => {
+                if !line.starts_with('*') {
+                    continue;
+                }
+
+                let mut words = template_words(line, &["l", "link", "syn"]);
+
+                if words.is_empty() {
+                    words = link_targets(line);
+                }
+
+                let definition = match word_data
+                    .meanings
+                    .last_mut()
+                    .and_then(|meaning| meaning.definitions.first_mut())
+                {
+                    Some(definition) => definition,
+                    None => continue,
+                };
+
+                let list = if let Section::Synonyms = section {
+                    &mut definition.synonyms
+                } else {
+                    &mut definition.antonyms
+                };
+
+                list.get_or_insert_with(Vec::new).extend(words);
+            }
+            Section::Other => {}
+        }
+    }
+
+    entries.push(word_data);
+
+    for word_data in entries.iter_mut() {
+        word_data
+            .meanings
+            .retain(|meaning| !meaning.definitions.is_empty());
+    }
+
+    entries
+}
+
+/// get the level and text of a `== heading ==`
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    if !line.starts_with('=') || !line.ends_with('=') {
+        return None;
+    }
+
+    let level = line.chars().take_while(|c| *c == '=').count();
+
+    let heading = line.trim_matches('=').trim();
+
+    if heading.is_empty() {
+        None
+    } else {
+        Some((level, heading))
+    }
+}
+
+/// split the templates with one of the names out of the line and return
+/// there positional arguments after the language code
+fn template_words(line: &str, names: &[&str]) -> Vec<String> {
+    let mut words = Vec::new();
+
+    let mut rest = line;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let params: Vec<&str> = rest[start + 2..end].split('|').collect();
+
+        if names.contains(&params[0].trim()) {
+            words.extend(
+                params
+                    .iter()
+                    .skip(2)
+                    .filter(|param| !param.contains('=') && !param.is_empty())
+                    .map(|param| {
+                        param
+                            .trim()
+                            .trim_start_matches("Thesaurus:")
+                            .to_string()
+                    }),
+            );
+        }
+
+        rest = &rest[end + 2..];
+    }
+
+    words
+}
+
+/// the targets of `[[links]]` in a line
+fn link_targets(line: &str) -> Vec<String> {
+    line.split("[[")
+        .skip(1)
+        .filter_map(|part| part.split("]]").next())
+        .map(|link| link.split('|').next().unwrap_or(link).to_string())
+        .filter(|link| !link.contains(':'))
+        .collect()
+}
+
+/// turn wikitext in to plain text by replacing the common templates with
+/// there text and dropping the rest
+fn clean_wikitext(text: &str) -> String {
+    let mut text = text.to_string();
+
+    // remove references first since they can have anything in them
+    while let Some(start) = text.find("<ref") {
+        let end = match (text[start..].find("/>"), text[start..].find("</ref>"))
+        {
+            (Some(short), Some(long)) if short < long => start + short + 2,
+            (_, Some(long)) => start + long + 6,
+            (Some(short), None) => start + short + 2,
+            (None, None) => text.len(),
+        };
+
+        text.replace_range(start..end, "");
+    }
+
+    // links have pipes in them so do them before templates
+    while let Some(start) = text.find("[[") {
+        let end = match text[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let link = text[start + 2..end].to_string();
+
+        let shown = link.rsplit('|').next().unwrap_or(&link).to_string();
+
+        text.replace_range(start..end + 2, &shown);
+    }
+
+    // do the inner most template first so nested templates work
+    while let Some(start) = text.rfind("{{") {
+        let end = match text[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let template = text[start + 2..end].to_string();
+
+        text.replace_range(start..end + 2, &render_template(&template));
+    }
+
+    text = text.replace("'''", "").replace("''", "");
+
+    // drop any html left over
+    let mut output = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+
+    output.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn render_template(template: &str) -> String {
+    let params: Vec<&str> = template
+        .split('|')
+        .map(|param| param.trim())
+        .filter(|param| !param.contains('='))
+        .collect();
+
+    let param = |i: usize| params.get(i).copied().unwrap_or("").to_string();
+
+    match params[0] {
+        "l" | "link" | "m" | "mention" | "l-self" => {
+            // the shown text can be given after the word
+            let shown = param(3);
+
+            if shown.is_empty() {
+                param(2)
+            } else {
+                shown
+            }
+        }
+        "lb" | "lbl" | "label" | "term-label" => format!(
+            "({})",
+            params
+                .iter()
+                .skip(2)
+                .filter(|p| **p != "_")
+                .copied()
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        "gloss" | "gl" => format!("({})", param(1)),
+        "ux" | "uxi" | "usex" => param(2),
+        "w" | "pedia" | "non-gloss definition" | "n-g" | "ngd" => param(1),
+        "inh" | "inherited" | "der" | "derived" | "bor" | "borrowed" => {
+            param(3)
+        }
+        "cog" | "cognate" | "noncog" => param(2),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_utils::TempSetup;
+
+    fn fake_jsonl() -> String {
+        [
+            r#"{"word": "flagrant", "pos": "adj", "lang_code": "en", "etymology_text": "From Latin flagrāns.", "sounds": [{"ipa": "/ˈfleɪɡɹənt/"}, {"mp3_url": "https://example.com/flagrant.mp3"}], "senses": [{"glosses": ["Obvious and offensive."], "examples": [{"text": "a flagrant violation"}], "synonyms": [{"word": "blatant"}]}]}"#,
+            r#"{"word": "flagrant", "pos": "noun", "lang_code": "en", "senses": [{"glosses": ["A flagrant foul.", "In basketball, a flagrant foul."]}], "antonyms": [{"word": "subtle"}]}"#,
+            r#"{"word": "flagrant", "pos": "adj", "lang_code": "fr", "senses": [{"glosses": ["flagrant"]}]}"#,
+            r#"{"word": "flagrantly", "pos": "adv", "lang_code": "en", "senses": [{"glosses": ["In a flagrant manner."]}]}"#,
+            r#"{"word": "nothing", "pos": "noun", "lang_code": "en", "senses": [{"tags": ["no-gloss"]}]}"#,
+        ]
+        .join("\n")
+    }
+
+    fn fake_xml() -> String {
+        r#"<mediawiki>
+  <page>
+    <title>flagrant</title>
+    <ns>0</ns>
+    <revision>
+      <text bytes="100" xml:space="preserve">==English==
+===Etymology===
+From {{bor|en|la|flagrāns}}, from {{m|la|flagrō||to burn}}.
+
+===Pronunciation===
+* {{IPA|en|/ˈfleɪ.ɡɹənt/|/ˈflæɡ.ɹənt/}}
+
+===Adjective===
+{{en-adj}}
+
+# {{lb|en|of something bad}} [[obvious|Obvious]] and '''offensive'''.&lt;ref&gt;a book&lt;/ref&gt;
+#: {{syn|en|blatant|glaring}}
+#: {{ux|en|a flagrant violation}}
+#* 1900, someone, a long quotation
+
+====Antonyms====
+* {{l|en|subtle}}
+
+==French==
+===Adjective===
+# flagrant
+</text>
+    </revision>
+  </page>
+  <page>
+    <title>Wiktionary:Main Page</title>
+    <ns>4</ns>
+    <revision>
+      <text bytes="10" xml:space="preserve">==English==
+===Noun===
+# not a word
+</text>
+    </revision>
+  </page>
+  <page>
+    <title>empty</title>
+    <ns>0</ns>
+    <revision>
+      <text bytes="0" xml:space="preserve" />
+    </revision>
+  </page>
+</mediawiki>
+"#
+        .to_string()
+    }
+
+    fn import_entries(
+        input: &str,
+        format: &DumpFormat,
+        lang: &str,
+    ) -> Vec<Vec<WordData>> {
+        let mut words = Vec::new();
+
+        let count = import_dump(input.as_bytes(), format, lang, |entries| {
+            words.push(entries);
+
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count.words, words.len(), "returned the wrong count");
+        assert_eq!(count.skipped, 0, "skipped lines");
+
+        words
+    }
+
+    // only the first entry for each word
+    fn import_all(
+        input: &str,
+        format: &DumpFormat,
+        lang: &str,
+    ) -> Vec<WordData> {
+        import_entries(input, format, lang)
+            .into_iter()
+            .map(|mut entries| entries.remove(0))
+            .collect()
+    }
+
+    #[test]
+    fn test_dump_format_from_path() {
+        assert_eq!(DumpFormat::from_path("dump.xml"), DumpFormat::Xml);
+        assert_eq!(DumpFormat::from_path("dump.xml.gz"), DumpFormat::Xml);
+        assert_eq!(DumpFormat::from_path("dump.jsonl"), DumpFormat::Jsonl);
+        assert_eq!(DumpFormat::from_path("-"), DumpFormat::Jsonl);
+    }
+
+    #[test]
+    fn test_import_jsonl() {
+        let words = import_all(&fake_jsonl(), &DumpFormat::Jsonl, "en");
+
+        let names: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+
+        assert_eq!(
+            names,
+            vec!["flagrant", "flagrantly"],
+            "did not merge or filter the entries"
+        );
+
+        let flagrant = &words[0];
+
+        assert_eq!(flagrant.phonetics[0].text, "/ˈfleɪɡɹənt/");
+        assert_eq!(
+            flagrant.phonetics[0].audio,
+            "https://example.com/flagrant.mp3"
+        );
+        assert_eq!(flagrant.etymology.as_deref(), Some("From Latin flagrāns."));
+
+        assert_eq!(flagrant.meanings.len(), 2, "did not merge the entries");
+        assert_eq!(flagrant.meanings[0].partOfSpeech, "adjective");
+
+        let adjective = &flagrant.meanings[0].definitions[0];
+
+        assert_eq!(adjective.definition, "Obvious and offensive.");
+        assert_eq!(adjective.example.as_deref(), Some("a flagrant violation"));
+        assert_eq!(adjective.synonyms, Some(vec!["blatant".to_string()]));
+
+        let noun = &flagrant.meanings[1].definitions[0];
+
+        assert_eq!(noun.definition, "In basketball, a flagrant foul.");
+        assert_eq!(noun.antonyms, Some(vec!["subtle".to_string()]));
+    }
+
+    #[test]
+    fn test_import_jsonl_other_language() {
+        let words = import_all(&fake_jsonl(), &DumpFormat::Jsonl, "fr");
+
+        assert_eq!(words.len(), 1, "did not filter by language");
+        assert_eq!(words[0].meanings[0].definitions[0].definition, "flagrant");
+    }
+
+    #[test]
+    fn test_import_xml() {
+        let words = import_all(&fake_xml(), &DumpFormat::Xml, "en");
+
+        assert_eq!(words.len(), 1, "imported pages it should not have");
+
+        let flagrant = &words[0];
+
+        assert_eq!(flagrant.word, "flagrant");
+
+        assert_eq!(
+            flagrant.etymology.as_deref(),
+            Some("From flagrāns, from flagrō."),
+            "did not clean the etymology"
+        );
+
+        let ipa: Vec<&str> =
+            flagrant.phonetics.iter().map(|p| p.text.as_str()).collect();
+
+        assert_eq!(ipa, vec!["/ˈfleɪ.ɡɹənt/", "/ˈflæɡ.ɹənt/"]);
+
+        assert_eq!(flagrant.meanings.len(), 1);
+        assert_eq!(flagrant.meanings[0].partOfSpeech, "adjective");
+
+        let definition = &flagrant.meanings[0].definitions[0];
+
+        assert_eq!(
+            definition.definition, "(of something bad) Obvious and offensive.",
+            "did not clean the definition"
+        );
+        assert_eq!(definition.example.as_deref(), Some("a flagrant violation"));
+        assert_eq!(
+            definition.synonyms,
+            Some(vec!["blatant".to_string(), "glaring".to_string()])
+        );
+        assert_eq!(definition.antonyms, Some(vec!["subtle".to_string()]));
+    }
+
+    #[test]
+    fn test_import_xml_other_language() {
+        let words = import_all(&fake_xml(), &DumpFormat::Xml, "fr");
+
+        assert_eq!(words.len(), 1, "did not find the french section");
+        assert_eq!(words[0].meanings[0].definitions[0].definition, "flagrant");
+        assert!(words[0].etymology.is_none(), "used the english etymology");
+    }
+
+    #[test]
+    fn test_import_jsonl_etymologies() {
+        let input = [
+            r#"{"word": "bass", "pos": "noun", "lang_code": "en", "etymology_number": 1, "etymology_text": "From Middle English bace.", "senses": [{"glosses": ["A low sound."]}]}"#,
+            r#"{"word": "bass", "pos": "noun", "lang_code": "en", "etymology_number": 2, "etymology_text": "From Old English bærs.", "senses": [{"glosses": ["A fish."]}]}"#,
+            r#"{"word": "bass", "pos": "adj", "lang_code": "en", "etymology_number": 1, "senses": [{"glosses": ["Low in pitch."]}]}"#,
+            r#"{"word": "bass", "pos": "name", "lang_code": "en", "senses": [{"glosses": ["A surname."]}]}"#,
+        ]
+        .join("\n");
+
+        let words = import_entries(&input, &DumpFormat::Jsonl, "en");
+
+        assert_eq!(words.len(), 1, "did not keep the entries together");
+
+        let entries = &words[0];
+
+        assert_eq!(entries.len(), 3, "did not split by etymology");
+        assert_eq!(entries[0].meanings.len(), 2);
+        assert_eq!(
+            entries[0].meanings[1].definitions[0].definition,
+            "Low in pitch."
+        );
+        assert_eq!(entries[1].meanings[0].definitions[0].definition, "A fish.");
+        assert_eq!(
+            entries[2].meanings[0].definitions[0].definition, "A surname.",
+            "merged a line with out an etymology in to another one"
+        );
+    }
+
+    #[test]
+    fn test_import_jsonl_case_headwords() {
+        let input = [
+            r#"{"word": "bass", "pos": "noun", "lang_code": "en", "senses": [{"glosses": ["A fish."]}]}"#,
+            r#"{"word": "Bass", "pos": "name", "lang_code": "en", "senses": [{"glosses": ["A surname."]}]}"#,
+            r#"{"word": "bassoon", "pos": "noun", "lang_code": "en", "senses": [{"glosses": ["An instrument."]}]}"#,
+        ]
+        .join("\n");
+
+        let words = import_entries(&input, &DumpFormat::Jsonl, "en");
+
+        assert_eq!(words.len(), 2, "did not keep bass and Bass together");
+
+        let names: Vec<&str> =
+            words[0].iter().map(|entry| entry.word.as_str()).collect();
+
+        assert_eq!(names, vec!["bass", "Bass"]);
+    }
+
+    #[test]
+    fn test_save_import_case_headwords() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        let cache = Cache::open_at(&root_path.join("cache.db")).unwrap();
+
+        let mut saved = HashSet::new();
+        let mut replaced = 0;
+
+        // flagrant was cached before and is replaced
+        cache
+            .cache_definition("flagrant", &[new_entry("flagrant")], "test")
+            .unwrap();
+
+        // pages in a XML dump are not next to each other
+        let input = fake_xml()
+            .replace("<title>empty</title>", "<title>Flagrant</title>")
+            .replace(
+                r#"<text bytes="0" xml:space="preserve" />"#,
+                "<text>==English==\n===Proper noun===\n# A surname.\n</text>",
+            );
+
+        let count =
+            import_dump(input.as_bytes(), &DumpFormat::Xml, "en", |entries| {
+                save_import(&cache, &mut saved, &mut replaced, entries)
+            })
+            .unwrap();
+
+        assert_eq!(count.words, 2, "did not save both pages");
+        assert_eq!(saved.len(), 1, "did not fold the pages in to one word");
+        assert_eq!(replaced, 1, "did not count the replaced word once");
+
+        let entries = cache.peek("flagrant").unwrap().unwrap().entries;
+
+        let names: Vec<&str> =
+            entries.iter().map(|entry| entry.word.as_str()).collect();
+
+        assert_eq!(names, vec!["flagrant", "Flagrant"], "lost an entry");
+    }
+
+    #[test]
+    fn test_import_jsonl_skips_bad_lines() {
+        let mut lines: Vec<String> =
+            fake_jsonl().lines().map(String::from).collect();
+
+        lines.insert(1, r#"{"word": "flagrant", "pos": "#.to_string());
+        lines.insert(3, "not json at all".to_string());
+
+        let mut words = Vec::new();
+
+        let count = import_dump(
+            lines.join("\n").as_bytes(),
+            &DumpFormat::Jsonl,
+            "en",
+            |entries| {
+                words.push(entries);
+
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            count,
+            ImportCount {
+                words: 2,
+                replaced: 0,
+                skipped: 2
+            }
+        );
+        assert_eq!(words[0][0].meanings.len(), 2, "lost the lines after");
+    }
+
+    #[test]
+    fn test_import_xml_etymologies() {
+        let input = r#"<page>
+    <title>lead</title>
+    <ns>0</ns>
+    <text xml:space="preserve">==English==
+===Etymology 1===
+From Old English lǣdan.
+
+====Verb====
+# To guide.
+
+===Etymology 2===
+From Old English lēad.
+
+====Noun====
+# A heavy metal.
+</text>
+</page>
+"#;
+
+        let words = import_entries(input, &DumpFormat::Xml, "en");
+
+        assert_eq!(words.len(), 1);
+
+        let entries = &words[0];
+
+        assert_eq!(entries.len(), 2, "did not split by etymology");
+        assert_eq!(
+            entries[0].etymology.as_deref(),
+            Some("From Old English lǣdan.")
+        );
+        assert_eq!(entries[1].meanings[0].partOfSpeech, "noun");
+        assert_eq!(
+            entries[1].meanings[0].definitions[0].definition,
+            "A heavy metal."
+        );
+    }
+
+    #[test]
+    fn test_clean_wikitext() {
+        assert_eq!(
+            clean_wikitext(
+                "{{lb|en|transitive|_|figurative}} To [[burn#English|burn]] \
+                 {{gloss|a {{m|en|fire}}}}.<ref name=\"a\" />"
+            ),
+            "(transitive, figurative) To burn (a fire).",
+            "did not clean the wikitext"
+        );
+    }
+}
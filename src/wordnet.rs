@@ -0,0 +1,569 @@
+//! read definitions from a local WordNet 3.x database
+//!
+//! a WordNet database is a directory with an `index.<pos>` and `data.<pos>`
+//! file for each part of speech, the index files are sorted so a lemma can be
+//! found with a binary search and each index line points at the byte offsets
+//! of its synsets in the data file
+use std::{
+    cmp::Ordering,
+    env, fs,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    dict_api::{Definition, Meaning, WordData},
+    error::WordsError,
+    provider::DictionaryProvider,
+};
+
+/// the file suffixes and the names to show for each part of speech
+const PARTS_OF_SPEECH: [(&str, &str); 4] = [
+    ("noun", "noun"),
+    ("verb", "verb"),
+    ("adj", "adjective"),
+    ("adv", "adverb"),
+];
+
+/// get definitions from a WordNet database directory
+pub struct WordNet {
+    path: PathBuf,
+}
+
+/// a synset parsed from a line in a data file
+struct Synset {
+    words: Vec<String>,
+    // (pointer symbol, synset offset, pos, source word, target word)
+    pointers: Vec<(String, u64, String, usize, usize)>,
+    gloss: String,
+}
+
+impl WordNet {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn data_path(&self, pos: &str) -> PathBuf {
+        // satellite adjectives live in the adjective files
+        let pos = match pos {
+            "n" => "noun",
+            "v" => "verb",
+            "a" | "s" => "adj",
+            _ => "adv",
+        };
+
+        self.path.join(format!("data.{}", pos))
+    }
+
+    fn read_synset(
+        &self,
+        data_path: &Path,
+        offset: u64,
+    ) -> Result<Synset, WordsError> {
+        let read_line = || -> io::Result<String> {
+            let mut data_file = BufReader::new(fs::File::open(data_path)?);
+
+            data_file.seek(SeekFrom::Start(offset))?;
+
+            let mut line = String::new();
+
+            data_file.read_line(&mut line)?;
+
+            Ok(line)
+        };
+
+        let line = read_line().map_err(|e| read_error(data_path, e))?;
+
+        parse_synset(&line).ok_or_else(|| {
+            WordsError::Parse(format!(
+                "malformed synset at {} in {}",
+                offset,
+                data_path.display()
+            ))
+        })
+    }
+
+    /// turn the antonym pointers from the queried word in to words
+    fn antonyms(
+        &self,
+        synset: &Synset,
+        word_num: usize,
+    ) -> Result<Vec<String>, WordsError> {
+        let mut antonyms = Vec::new();
+
+        for (symbol, offset, pos, source, target) in &synset.pointers {
+            if symbol != "!" || (*source != 0 && *source != word_num) {
+                continue;
+            }
+
+            let target_synset =
+                self.read_synset(&self.data_path(pos), *offset)?;
+
+            let target_words = if *target == 0 {
+                target_synset.words
+            } else {
+                target_synset
+                    .words
+                    .into_iter()
+                    .skip(target - 1)
+                    .take(1)
+                    .collect()
+            };
+
+            antonyms.extend(target_words);
+        }
+
+        Ok(antonyms)
+    }
+}
+
+impl DictionaryProvider for WordNet {
+    fn get_definition(&self, query: &str) -> Result<Vec<WordData>, WordsError> {
+        let lemma = query.trim().to_lowercase().replace(' ', "_");
+
+        let mut meanings = Vec::new();
+
+        for (pos, part_of_speech) in PARTS_OF_SPEECH.iter() {
+            let index_path = self.path.join(format!("index.{}", pos));
+
+            if !index_path.exists() {
+                continue;
+            }
+
+            let index_line = match search_index(&index_path, &lemma)
+                .map_err(|e| read_error(&index_path, e))?
+            {
+                Some(line) => line,
+                None => continue,
+            };
+
+            let data_path = self.path.join(format!("data.{}", pos));
+
+            let mut definitions = Vec::new();
+
+            for offset in parse_index_offsets(&index_line) {
+                let synset = self.read_synset(&data_path, offset)?;
+
+                let word_num = synset
+                    .words
+                    .iter()
+                    .position(|w| w.to_lowercase().replace(' ', "_") == lemma)
+                    .map_or(0, |i| i + 1);
+
+                let antonyms = self.antonyms(&synset, word_num)?;
+
+                let synonyms: Vec<String> = synset
+                    .words
+                    .into_iter()
+                    .filter(|w| w.to_lowercase().replace(' ', "_") != lemma)
+                    .collect();
+
+                let (definition, examples) = split_gloss(&synset.gloss);
+
+                definitions.push(Definition {
+                    definition,
+                    example: if examples.is_empty() {
+                        None
+                    } else {
+                        Some(examples.join("; "))
+                    },
+                    synonyms: if synonyms.is_empty() {
+                        None
+                    } else {
+                        Some(synonyms)
+                    },
+                    antonyms: if antonyms.is_empty() {
+                        None
+                    } else {
+                        Some(antonyms)
+                    },
+                });
+            }
+
+            meanings.push(Meaning {
+                partOfSpeech: part_of_speech.to_string(),
+                definitions,
+            });
+        }
+
+        if meanings.is_empty() {
+            return Err(WordsError::NotFound(
+                "No Definitions Found".to_string(),
+            ));
+        }
+
+        Ok(vec![WordData {
+            word: lemma.replace('_', " "),
+            phonetics: Vec::new(),
+            meanings,
+            etymology: None,
+        }])
+    }
+}
+
+/// use WNSEARCHDIR like the wordnet tools do else the usual system path
+pub fn default_wordnet_path() -> PathBuf {
+    match env::var("WNSEARCHDIR") {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from("/usr/share/wordnet"),
+    }
+}
+
+// a database file that cant be read is most likely a bad wordnet_path
+fn read_error(path: &Path, err: io::Error) -> WordsError {
+    WordsError::Config(format!("cant read {}: {}", path.display(), err))
+}
+
+/// binary search a sorted index file for the line starting with the lemma
+fn search_index(index_path: &Path, lemma: &str) -> io::Result<Option<String>> {
+    let mut index_file = BufReader::new(fs::File::open(index_path)?);
+
+    let mut low = 0;
+    let mut high = index_file.get_ref().metadata()?.len();
+
+    let mut line = String::new();
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        // find the start of the first line at or after mid
+        let start = if mid == 0 {
+            0
+        } else {
+            index_file.seek(SeekFrom::Start(mid - 1))?;
+
+            line.clear();
+
+            mid - 1 + index_file.read_line(&mut line)? as u64
+        };
+
+        if start >= high {
+            high = mid;
+            continue;
+        }
+
+        index_file.seek(SeekFrom::Start(start))?;
+
+        line.clear();
+
+        let line_len = index_file.read_line(&mut line)? as u64;
+
+        let key = line.split(' ').next().unwrap_or("");
+
+        match key.cmp(lemma) {
+            Ordering::Equal => return Ok(Some(line.trim_end().to_string())),
+            Ordering::Less => low = start + line_len,
+            Ordering::Greater => high = mid,
+        }
+    }
+
+    Ok(None)
+}
+
+/// get the synset offsets from an index line
+///
+/// `lemma pos synset_cnt p_cnt [ptr_symbol...] sense_cnt tagsense_cnt
+/// synset_offset [synset_offset...]`
+fn parse_index_offsets(line: &str) -> Vec<u64> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+
+    let synset_cnt = fields
+        .get(2)
+        .and_then(|f| f.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    fields
+        .iter()
+        .rev()
+        .take(synset_cnt)
+        .rev()
+        .filter_map(|f| f.parse::<u64>().ok())
+        .collect()
+}
+
+/// parse a data line
+///
+/// `synset_offset lex_filenum ss_type w_cnt word lex_id [word lex_id...]
+/// p_cnt [ptr...] [frames...] | gloss`
+fn parse_synset(line: &str) -> Option<Synset> {
+    let mut parts = line.splitn(2, " | ");
+
+    let fields: Vec<&str> = parts.next()?.split_whitespace().collect();
+
+    let gloss = parts.next().unwrap_or("").trim().to_string();
+
+    let w_cnt = usize::from_str_radix(fields.get(3)?, 16).ok()?;
+
+    let mut words = Vec::with_capacity(w_cnt);
+
+    for i in 0..w_cnt {
+        let word = fields.get(4 + i * 2)?;
+
+        // adjectives can have a syntactic marker like `(a)` or `(ip)`
+        let word = match word.find('(') {
+            Some(i) => &word[..i],
+            None => word,
+        };
+
+        words.push(word.replace('_', " "));
+    }
+
+    let p_start = 4 + w_cnt * 2;
+
+    let p_cnt = fields.get(p_start)?.parse::<usize>().ok()?;
+
+    let mut pointers = Vec::with_capacity(p_cnt);
+
+    for i in 0..p_cnt {
+        let ptr = p_start + 1 + i * 4;
+
+        let source_target = fields.get(ptr + 3)?;
+
+        pointers.push((
+            fields.get(ptr)?.to_string(),
+            fields.get(ptr + 1)?.parse::<u64>().ok()?,
+            fields.get(ptr + 2)?.to_string(),
+            usize::from_str_radix(source_target.get(..2)?, 16).ok()?,
+            usize::from_str_radix(source_target.get(2..)?, 16).ok()?,
+        ));
+    }
+
+    Some(Synset {
+        words,
+        pointers,
+        gloss,
+    })
+}
+
+/// split a gloss in to the definition and the quoted examples
+fn split_gloss(gloss: &str) -> (String, Vec<String>) {
+    let mut definition = Vec::new();
+    let mut examples = Vec::new();
+
+    // parts are split on `;` but not the ones inside an example
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+
+    for (i, c) in gloss.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&gloss[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    parts.push(&gloss[start..]);
+
+    for part in parts {
+        let part = part.trim();
+
+        if part.starts_with('"') {
+            examples.push(part.trim_matches('"').to_string());
+        } else if !part.is_empty() {
+            definition.push(part);
+        }
+    }
+
+    (definition.join("; "), examples)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Write;
+
+    use crate::test_utils::TempSetup;
+
+    // build a data file where the offset field of each synset is the byte
+    // offset of the line, `{n}` in a synset is replaced with the offset of
+    // the nth synset
+    fn fake_data_file(synsets: &[&str]) -> (String, Vec<u64>) {
+        let mut offsets = Vec::new();
+
+        // the license header at the top of real data files
+        let mut offset = "  1 fake header\n".len() as u64;
+
+        for synset in synsets {
+            offsets.push(offset);
+
+            // each `{n}` will be replaced with an 8 digit offset
+            let placeholders = synset.matches('{').count() * 5;
+
+            offset +=
+                (format!("{:08} {}\n", 0, synset).len() + placeholders) as u64;
+        }
+
+        let mut data = "  1 fake header\n".to_string();
+
+        for (i, synset) in synsets.iter().enumerate() {
+            let mut synset = synset.to_string();
+
+            for (n, other) in offsets.iter().enumerate() {
+                synset = synset
+                    .replace(&format!("{{{}}}", n), &format!("{:08}", other));
+            }
+
+            data.push_str(&format!("{:08} {}\n", offsets[i], synset));
+        }
+
+        (data, offsets)
+    }
+
+    fn fake_wordnet(root: &Path) {
+        let (data, offsets) = fake_data_file(&[
+            "00 a 02 good 0 full 0 001 ! {1} a 0101 | having desirable \
+             qualities; \"good news\"; \"a good report card\"",
+            "00 a 01 bad 0 001 ! {0} a 0101 | having undesirable qualities",
+            "00 a 01 dull 0 000 | not bright",
+        ]);
+
+        let index = format!(
+            "  1 fake header\n\
+             bad a 1 1 ! 1 0 {:08}  \n\
+             dull a 1 0 1 0 {:08}  \n\
+             good a 1 1 ! 1 0 {:08}  \n",
+            offsets[1], offsets[2], offsets[0]
+        );
+
+        fs::File::create(root.join("data.adj"))
+            .unwrap()
+            .write_all(data.as_bytes())
+            .unwrap();
+
+        fs::File::create(root.join("index.adj"))
+            .unwrap()
+            .write_all(index.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_synset() {
+        let synset = parse_synset(
+            "00001740 00 a 02 able 0 good(a) 0 001 ! 00002098 a 0101 | (usually \
+             followed by `to') having the necessary means; \"able to swim\"",
+        )
+        .unwrap();
+
+        assert_eq!(synset.words, vec!["able", "good"], "got the wrong words");
+
+        assert_eq!(
+            synset.pointers,
+            vec![("!".to_string(), 2098, "a".to_string(), 1, 1)],
+            "got the wrong pointers"
+        );
+
+        let (definition, examples) = split_gloss(&synset.gloss);
+
+        assert_eq!(
+            definition, "(usually followed by `to') having the necessary means",
+            "did not split the definition from the gloss"
+        );
+
+        assert_eq!(examples, vec!["able to swim"], "got the wrong examples");
+    }
+
+    #[test]
+    fn test_split_gloss_quoted_semicolons() {
+        let (definition, examples) = split_gloss(
+            "move fast; go quickly; \"run; don't walk\"; \"he ran home\"",
+        );
+
+        assert_eq!(definition, "move fast; go quickly");
+
+        assert_eq!(
+            examples,
+            vec!["run; don't walk", "he ran home"],
+            "split an example on its semicolon"
+        );
+    }
+
+    #[test]
+    fn test_search_index() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_wordnet(&root_path);
+
+        let index_path = root_path.join("index.adj");
+
+        for word in &["bad", "dull", "good"] {
+            let line = search_index(&index_path, word).unwrap();
+
+            assert!(
+                line.unwrap().starts_with(word),
+                "found the wrong line for {}",
+                word
+            );
+        }
+
+        for word in &["a", "cat", "zebra"] {
+            assert!(
+                search_index(&index_path, word).unwrap().is_none(),
+                "found a line for {} that is not in the index",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_definition() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_wordnet(&root_path);
+
+        let wordnet = WordNet::new(root_path);
+
+        let word_data = wordnet.get_definition("Good").unwrap().remove(0);
+
+        assert_eq!(word_data.word, "good", "got the wrong word");
+
+        assert_eq!(word_data.meanings.len(), 1, "got the wrong meanings");
+
+        let meaning = &word_data.meanings[0];
+
+        assert_eq!(meaning.partOfSpeech, "adjective", "got the wrong pos");
+
+        let definition = &meaning.definitions[0];
+
+        assert_eq!(definition.definition, "having desirable qualities");
+
+        assert_eq!(
+            definition.example.as_deref(),
+            Some("good news; a good report card"),
+            "did not get the examples"
+        );
+
+        assert_eq!(
+            definition.synonyms,
+            Some(vec!["full".to_string()]),
+            "did not get the synonyms"
+        );
+
+        assert_eq!(
+            definition.antonyms,
+            Some(vec!["bad".to_string()]),
+            "did not follow the antonym pointer"
+        );
+    }
+
+    #[test]
+    fn test_get_definition_not_found() {
+        let mut temp = TempSetup::default();
+        let root_path = temp.setup();
+
+        fake_wordnet(&root_path);
+
+        let wordnet = WordNet::new(root_path);
+
+        match wordnet.get_definition("flgrent") {
+            Ok(_) => panic!("found a word that is not in the database"),
+            Err(err) => assert_eq!(err.to_string(), "No Definitions Found"),
+        }
+    }
+}
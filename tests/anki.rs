@@ -0,0 +1,35 @@
+//! write cached words as flash cards with `anki`
+mod support;
+
+use support::{stderr, stdout, MockServer, TestHome, DEAD_URL};
+
+#[test]
+fn test_anki() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    home.run(&server, &["-d", "bass"]);
+
+    let output = home.run_with_url(DEAD_URL, &["anki", "--since", "1h"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let cards = stdout(&output);
+    let lines: Vec<&str> = cards.lines().collect();
+
+    assert_eq!(lines.len(), 3, "{}", cards);
+    assert!(lines[2].starts_with("bass"), "{}", cards);
+    assert!(lines[2].contains("A perch-like fish."), "{}", cards);
+
+    let path = home.data_path().join("cards.tsv");
+    let path = path.to_str().unwrap();
+
+    let output = home.run_with_url(DEAD_URL, &["anki", "bass", "-o", path]);
+
+    assert!(
+        stdout(&output).contains("wrote 1 cards"),
+        "{}",
+        stderr(&output)
+    );
+    assert_eq!(std::fs::read_to_string(path).unwrap(), cards);
+}
@@ -0,0 +1,42 @@
+//! look at and clean up the cache with the `cache` command
+mod support;
+
+use support::{stderr, stdout, MockServer, TestHome};
+
+#[test]
+fn test_cache_command() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    home.run(&server, &["-d", "bass"]);
+    home.run(&server, &["-d", "bass"]);
+
+    let output = home.run(&server, &["cache", "list", "ba*"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stdout(&output).starts_with("bass\tdictionaryapi\t"),
+        "did not list the word: {}",
+        stdout(&output)
+    );
+
+    let output = home.run(&server, &["cache", "show", "bass"]);
+
+    assert!(stdout(&output).contains("A perch-like fish."));
+
+    let output = home.run(&server, &["cache", "stats"]);
+
+    let out = stdout(&output);
+
+    assert!(out.contains("words\t1"), "wrong stats: {}", out);
+    assert!(out.contains("  bass\t1"), "did not count the hit: {}", out);
+
+    let output = home.run(&server, &["cache", "rm", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(home.cached_source("bass").is_none(), "did not remove bass");
+
+    let output = home.run(&server, &["cache", "rm", "bass"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+}
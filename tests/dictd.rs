@@ -0,0 +1,93 @@
+//! run `-d` against a local dict server with more then one database
+mod support;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+
+use support::{stderr, stdout, TestHome, DEAD_URL};
+
+// a dict server where `bass` is a fish in `wn` and a sound in `gcide`
+fn fake_dictd() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut writer = stream.unwrap();
+            let mut reader = BufReader::new(writer.try_clone().unwrap());
+
+            writer.write_all(b"220 fake dictd <1@fake>\r\n").unwrap();
+
+            let mut line = String::new();
+
+            while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                let response = match line.trim_end() {
+                    "DEFINE wn bass" => {
+                        "150 1 definitions retrieved\r\n\
+                         151 \"bass\" wn \"WordNet\"\r\n\
+                         bass\r\n    n 1: a perch-like fish\r\n\
+                         .\r\n\
+                         250 ok\r\n"
+                    }
+                    "DEFINE gcide bass" => {
+                        "150 1 definitions retrieved\r\n\
+                         151 \"bass\" gcide \"GCIDE\"\r\n\
+                         bass\r\n    n 1: a low sound\r\n\
+                         .\r\n\
+                         250 ok\r\n"
+                    }
+                    _ => "250 ok\r\n",
+                };
+
+                writer.write_all(response.as_bytes()).unwrap();
+
+                line.clear();
+            }
+        }
+    });
+
+    port
+}
+
+#[test]
+fn test_define_in_two_databases() {
+    let port = fake_dictd();
+    let home = TestHome::new();
+
+    home.write_config(&format!(
+        "[dictionary]\nprovider = \"dictd\"\n\
+         dictd_host = \"127.0.0.1\"\ndictd_port = {}\n",
+        port
+    ));
+
+    let output =
+        home.run_with_url(DEAD_URL, &["--database", "wn", "-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("a perch-like fish"));
+
+    let output =
+        home.run_with_url(DEAD_URL, &["--database", "gcide", "-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stdout(&output).contains("a low sound"),
+        "used the word cached from another database: {}",
+        stdout(&output)
+    );
+
+    let output = home.run_with_url(
+        DEAD_URL,
+        &["--offline", "--database", "wn", "-d", "bass"],
+    );
+
+    assert!(
+        stdout(&output).contains("a perch-like fish"),
+        "did not cache each database apart: {}",
+        stdout(&output)
+    );
+}
@@ -0,0 +1,258 @@
+//! run `-d` against a local dictionaryapi
+mod support;
+
+use std::time::Instant;
+
+use support::{stderr, stdout, MockServer, TestHome, DEAD_URL, SLOW_DELAY};
+
+#[test]
+fn test_define_every_entry() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let out = stdout(&output);
+
+    assert!(
+        out.contains("1. bass"),
+        "did not number the entries: {}",
+        out
+    );
+    assert!(
+        out.contains("2. bass"),
+        "did not number the entries: {}",
+        out
+    );
+    assert!(out.contains("A low spectrum of sound tones."));
+    assert!(out.contains("A perch-like fish."));
+
+    assert_eq!(
+        home.cached_source("bass").as_deref(),
+        Some("dictionaryapi"),
+        "did not cache the definition"
+    );
+}
+
+#[test]
+fn test_define_one_entry() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "bass", "--entry", "2"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let out = stdout(&output);
+
+    assert!(out.contains("A perch-like fish."));
+    assert!(!out.contains("A low spectrum"), "printed the other entry");
+
+    let output = home.run(&server, &["-d", "bass", "--entry", "3"]);
+
+    assert_eq!(output.status.code(), Some(9), "did not reject the entry");
+}
+
+#[test]
+fn test_define_not_found() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "missing"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stderr(&output).contains("No Definitions Found"));
+    assert!(home.cached_source("missing").is_none());
+}
+
+#[test]
+fn test_define_rate_limited() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "busy", "--retries", "0"]);
+
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("try again in 60 seconds"),
+        "did not say when to try again: {}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_define_malformed() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "broken"]);
+
+    assert_eq!(output.status.code(), Some(5), "{}", stderr(&output));
+}
+
+#[test]
+fn test_define_timeout() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let start = Instant::now();
+
+    let output = home.run(&server, &["-d", "slow", "--timeout", "1"]);
+
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    assert!(start.elapsed() < SLOW_DELAY, "did not give up waiting");
+}
+
+#[test]
+fn test_define_slow() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "slow"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("A perch-like fish."));
+}
+
+#[test]
+fn test_define_offline() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "bass", "--offline"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stderr(&output).contains("not in the cache"));
+
+    let output = home.run(&server, &["-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "bass", "--offline"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("A perch-like fish."));
+    assert!(stderr(&output).is_empty(), "tried the network");
+}
+
+#[test]
+fn test_define_in_two_languages() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "chat"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(stdout(&output).contains("An informal conversation."));
+
+    let output = home.run(&server, &["--lang", "fr", "-d", "chat"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stdout(&output).contains("Petit mammifère domestique."),
+        "used the english definition: {}",
+        stdout(&output)
+    );
+
+    // both are cached apart from each other
+    for (lang, definition) in &[
+        ("en", "An informal conversation."),
+        ("fr", "Petit mammifère domestique."),
+    ] {
+        let output = home.run_with_url(
+            DEAD_URL,
+            &["--lang", lang, "-d", "chat", "--offline"],
+        );
+
+        assert!(output.status.success(), "failed: {}", stderr(&output));
+        assert!(stdout(&output).contains(definition), "lost {}", lang);
+    }
+
+    // and the other providers dont get them
+    let output = home.run_with_url(
+        DEAD_URL,
+        &["--provider", "wordnet", "-d", "chat", "--offline"],
+    );
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+}
+
+#[test]
+fn test_define_refresh() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "bass", "--refresh"]);
+
+    // the fetch fails so the cached definition is used with a warning
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stderr(&output).contains("network error"),
+        "did not fetch again: {}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).contains("A perch-like fish."));
+}
+
+#[test]
+fn test_define_stale_fallback() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "bass"]);
+
+    assert!(stderr(&output).is_empty(), "fetched a fresh definition");
+
+    // everything is stale right away
+    home.write_config("[cache]\nttl = 0\n");
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "bass"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stderr(&output).contains("using a definition cached 0 days ago"),
+        "did not warn about the stale definition: {}",
+        stderr(&output)
+    );
+    assert!(stdout(&output).contains("A perch-like fish."));
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "missing"]);
+
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+}
+
+#[test]
+fn test_define_not_found_is_cached() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let output = home.run(&server, &["-d", "missing"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+
+    // the dictionary is not asked again
+    let output = home.run_with_url(DEAD_URL, &["-d", "missing"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stderr(&output).contains("No Definitions Found"));
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "missing", "--refresh"]);
+
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+
+    home.write_config("[cache]\nnot_found_ttl = 0\n");
+
+    let output = home.run_with_url(DEAD_URL, &["-d", "missing"]);
+
+    assert_eq!(output.status.code(), Some(3), "did not ask again");
+}
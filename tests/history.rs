@@ -0,0 +1,44 @@
+//! look back at lookups with `history`
+mod support;
+
+use support::{stderr, stdout, MockServer, TestHome, DEAD_URL};
+
+#[test]
+fn test_history() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    home.run(&server, &["-d", "bass"]);
+    home.run(&server, &["-d", "Bass"]);
+    home.run(&server, &["-d", "missing"]);
+
+    let output = home.run_with_url(DEAD_URL, &["history", "--since", "1h"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+
+    let lines: Vec<Vec<String>> = stdout(&output)
+        .lines()
+        .map(|line| line.split('\t').skip(1).map(String::from).collect())
+        .collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            vec!["define", "bass", "bass", "network", "found"],
+            vec!["define", "Bass", "bass", "cache", "found"],
+            vec!["define", "missing", "-", "-", "not found"],
+        ]
+    );
+
+    let output = home.run_with_url(DEAD_URL, &["history", "--until", "1h"]);
+
+    assert!(stdout(&output).is_empty(), "{}", stdout(&output));
+
+    let output = home.run_with_url(DEAD_URL, &["history", "--clear"]);
+
+    assert!(stdout(&output).contains("removed 3 lookups"));
+
+    let output = home.run_with_url(DEAD_URL, &["history"]);
+
+    assert!(stdout(&output).is_empty(), "{}", stdout(&output));
+}
@@ -0,0 +1,49 @@
+//! fill the cache from a word list with `prefetch`
+mod support;
+
+use support::{stderr, stdout, MockServer, TestHome, DEAD_URL};
+
+#[test]
+fn test_prefetch() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    let list = home.write_file("words.txt", "bass\nmissing\n\nbusy\nBass\n");
+
+    let output = home.run(
+        &server,
+        &["--retries", "0", "prefetch", &list, "--jobs", "2"],
+    );
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert_eq!(
+        stdout(&output).trim(),
+        "fetched 1, not found 1, failed 1, already cached 0"
+    );
+    assert!(
+        stderr(&output).contains("busy: rate limited"),
+        "did not show the failed word: {}",
+        stderr(&output)
+    );
+
+    assert_eq!(home.cached_source("bass").as_deref(), Some("dictionaryapi"));
+
+    let output = home.run_with_url(DEAD_URL, &["prefetch", &list]);
+
+    assert!(
+        stdout(&output).contains("already cached 2"),
+        "did not skip the cached and not found words: {}",
+        stdout(&output)
+    );
+
+    // remembering a word was not found for no time at all fetches it again
+    home.write_config("[cache]\nnot_found_ttl = 0\n");
+
+    let output = home.run_with_url(DEAD_URL, &["prefetch", &list]);
+
+    assert!(
+        stdout(&output).contains("failed 2, already cached 1"),
+        "did not fetch the expired not found word again: {}",
+        stdout(&output)
+    );
+}
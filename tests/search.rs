@@ -0,0 +1,25 @@
+//! find cached words by what they mean with `search`
+mod support;
+
+use support::{stderr, stdout, MockServer, TestHome, DEAD_URL};
+
+#[test]
+fn test_search() {
+    let server = MockServer::start();
+    let home = TestHome::new();
+
+    home.run(&server, &["-d", "bass"]);
+
+    let output = home.run_with_url(DEAD_URL, &["search", "fish like a perch"]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(
+        stdout(&output).starts_with("bass\t"),
+        "did not find the word: {}",
+        stdout(&output)
+    );
+
+    let output = home.run_with_url(DEAD_URL, &["search", "guitar"]);
+
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+}
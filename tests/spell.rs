@@ -0,0 +1,66 @@
+//! check words with `-s -` and `spell`
+mod support;
+
+use support::{stderr, stdout, TestHome, DEAD_URL};
+
+#[test]
+fn test_spell_lines() {
+    let home = TestHome::new();
+
+    home.fake_aspell();
+
+    let output = home.run_with_input(&["-s", "-"], "flgrent\n\nbass\nqzx\n");
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "flgrent\tflagrant, fragrant\nbass\tcorrect\nqzx\tno suggestions\n"
+    );
+
+    let path = home.write_file("words.txt", "bass\nflgrent\n");
+
+    let output = home.run_with_url(DEAD_URL, &["spell", &path]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "bass\tcorrect\nflgrent\tflagrant, fragrant\n"
+    );
+
+    let output = home.run_with_url(DEAD_URL, &["history"]);
+
+    assert_eq!(stdout(&output).lines().count(), 5, "{}", stdout(&output));
+}
+
+#[test]
+fn test_spell_document() {
+    let home = TestHome::new();
+
+    home.fake_aspell();
+
+    let path = home.write_file(
+        "README.md",
+        "# Title\nthe flgrent word\n```\nflgrent\n```\nsee `flgrent` and qzx \
+        here\n",
+    );
+
+    let output = home.run_with_url(DEAD_URL, &["spell", "--document", &path]);
+
+    assert_eq!(output.status.code(), Some(10), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "{0}:2:5: flgrent: flagrant, fragrant\n\
+            {0}:6:19: qzx: no suggestions\n",
+            path
+        )
+    );
+    assert!(stderr(&output).contains("found 2 misspelled words"));
+
+    let path = home.write_file("clean.md", "all good here\n");
+
+    let output = home.run_with_url(DEAD_URL, &["spell", "--document", &path]);
+
+    assert!(output.status.success(), "failed: {}", stderr(&output));
+    assert!(stdout(&output).is_empty());
+}
@@ -0,0 +1,304 @@
+//! a local stand in for dictionaryapi.dev
+//!
+//! the server answers with canned responses picked by the word at the end of
+//! the path so the tests never need the network
+//!
+//! - `bass` has two entries
+//! - `missing` is a 404 like the real api sends
+//! - `busy` is a 429 that asks us to wait a minute
+//! - `broken` is a 200 with a body that is not json
+//! - `slow` waits a few seconds before answering
+//! - `chat` is a conversation in english and a cat in french
+//!
+//! every test file has its own copy of this module and only uses some of it
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    thread,
+    time::Duration,
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use tempfile::TempDir;
+
+const BASS: &str = r#"[
+  {
+    "word": "bass",
+    "phonetics": [{"text": "/beɪs/", "audio": ""}],
+    "meanings": [
+      {
+        "partOfSpeech": "noun",
+        "definitions": [{"definition": "A low spectrum of sound tones."}]
+      }
+    ]
+  },
+  {
+    "word": "bass",
+    "phonetics": [{"text": "/bæs/", "audio": ""}],
+    "meanings": [
+      {
+        "partOfSpeech": "noun",
+        "definitions": [{"definition": "A perch-like fish."}]
+      }
+    ]
+  }
+]"#;
+
+const CHAT_EN: &str = r#"[
+  {
+    "word": "chat",
+    "phonetics": [],
+    "meanings": [
+      {
+        "partOfSpeech": "noun",
+        "definitions": [{"definition": "An informal conversation."}]
+      }
+    ]
+  }
+]"#;
+
+const CHAT_FR: &str = r#"[
+  {
+    "word": "chat",
+    "phonetics": [],
+    "meanings": [
+      {
+        "partOfSpeech": "nom masculin",
+        "definitions": [{"definition": "Petit mammifère domestique."}]
+      }
+    ]
+  }
+]"#;
+
+const NOT_FOUND: &str = r#"{"title": "No Definitions Found", "message": "Sorry pal, we couldn't find definitions for the word you were looking for.", "resolution": "You can try the search again at later time or head to the web instead."}"#;
+
+/// how long the `slow` word takes to answer
+pub const SLOW_DELAY: Duration = Duration::from_secs(3);
+
+/// nothing listens on port 1 so every request fails to connect
+pub const DEAD_URL: &str = "http://127.0.0.1:1/api/v2/entries";
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+pub struct MockServer {
+    addr: String,
+}
+
+impl MockServer {
+    /// start the server on a free port, it runs until the test exits
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let addr = listener.local_addr().unwrap().to_string();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || handle(stream));
+            }
+        });
+
+        Self { addr }
+    }
+
+    /// the url to give to `--api-url`
+    pub fn api_url(&self) -> String {
+        format!("http://{}/api/v2/entries", self.addr)
+    }
+}
+
+fn handle(stream: TcpStream) {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    // skip the headers
+    let mut line = String::new();
+
+    while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+        line.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let word = path.rsplit('/').next().unwrap_or("");
+
+    let response = match word {
+        "bass" => response("200 OK", "", BASS),
+        "busy" => response("429 Too Many Requests", "Retry-After: 60\r\n", ""),
+        "broken" => response("200 OK", "", "<html>not json</html>"),
+        "chat" if path.contains("/fr/") => response("200 OK", "", CHAT_FR),
+        "chat" => response("200 OK", "", CHAT_EN),
+        "slow" => {
+            thread::sleep(SLOW_DELAY);
+
+            response("200 OK", "", BASS)
+        }
+        _ => response("404 Not Found", "", NOT_FOUND),
+    };
+
+    let _ = reader.get_mut().write_all(response.as_bytes());
+}
+
+fn response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        body.len(),
+        headers,
+        body
+    )
+}
+
+/// a stand in for `aspell -a` that knows `flgrent` is misspelled and `qzx`
+/// has no suggestions, every other word is spelled right
+const FAKE_ASPELL: &str = r##"#!/bin/sh
+echo "@(#) International Ispell Version 3.1.20 (but really Aspell 0.60.8)"
+
+while IFS= read -r line; do
+    line="${line#^}"
+    offset=1
+
+    for word in $line; do
+        case "$word" in
+            flgrent) echo "& flgrent 2 $offset: flagrant, fragrant" ;;
+            qzx) echo "# qzx $offset" ;;
+            *) echo "*" ;;
+        esac
+
+        offset=$((offset + ${#word} + 1))
+    done
+
+    echo
+done
+"##;
+
+/// a home for the binary so it does not read the users config or cache
+pub struct TestHome {
+    dir: TempDir,
+}
+
+impl TestHome {
+    pub fn new() -> Self {
+        Self {
+            dir: TempDir::new().unwrap(),
+        }
+    }
+
+    pub fn data_path(&self) -> PathBuf {
+        self.dir.path().join("data").join("words_cli")
+    }
+
+    /// the source a word was cached from, if it was cached
+    pub fn cached_source(&self, word: &str) -> Option<String> {
+        let conn = Connection::open(self.data_path().join("cache.db")).unwrap();
+
+        conn.query_row(
+            "SELECT source FROM words WHERE word = ?1",
+            params![word],
+            |row| row.get(0),
+        )
+        .optional()
+        .unwrap()
+    }
+
+    /// write a file in the home and return its path
+    pub fn write_file(&self, name: &str, contents: &str) -> String {
+        let path = self.dir.path().join(name);
+
+        fs::write(&path, contents).unwrap();
+
+        path.to_string_lossy().to_string()
+    }
+
+    /// write a words_cli.toml for the binary to read
+    pub fn write_config(&self, config: &str) {
+        let config_path = self.dir.path().join("config").join("words_cli");
+
+        fs::create_dir_all(&config_path).unwrap();
+
+        fs::write(config_path.join("words_cli.toml"), config).unwrap();
+    }
+
+    /// put a fake aspell in the home that the binary will run instead of the
+    /// real one
+    pub fn fake_aspell(&self) {
+        let bin_path = self.dir.path().join("bin");
+
+        fs::create_dir_all(&bin_path).unwrap();
+
+        let aspell_path = bin_path.join("aspell");
+
+        fs::write(&aspell_path, FAKE_ASPELL).unwrap();
+        fs::set_permissions(&aspell_path, fs::Permissions::from_mode(0o755))
+            .unwrap();
+    }
+
+    /// run words_cli with the given args against the server
+    pub fn run(&self, server: &MockServer, args: &[&str]) -> Output {
+        self.run_with_url(&server.api_url(), args)
+    }
+
+    /// run words_cli with the given args against any url, like one where
+    /// nothing is listening
+    pub fn run_with_url(&self, api_url: &str, args: &[&str]) -> Output {
+        self.command(api_url, args).output().unwrap()
+    }
+
+    /// run words_cli with the given args and input on stdin
+    pub fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .command("http://127.0.0.1:1/api/v2/entries", args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // dropping stdin closes it so the binary sees the end of the input
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+
+        child.wait_with_output().unwrap()
+    }
+
+    fn command(&self, api_url: &str, args: &[&str]) -> Command {
+        // a fake aspell in the home is found first
+        let path = format!(
+            "{}:{}",
+            self.dir.path().join("bin").display(),
+            env::var("PATH").unwrap_or_default()
+        );
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_words_cli"));
+
+        command
+            .env("HOME", self.dir.path())
+            .env("XDG_DATA_HOME", self.dir.path().join("data"))
+            .env("XDG_CONFIG_HOME", self.dir.path().join("config"))
+            .env("PATH", path)
+            .args(["--api-url", api_url, "--no-style"])
+            .args(args);
+
+        command
+    }
+}